@@ -0,0 +1,304 @@
+use crate::lexer::Span;
+use std::fmt;
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Error => write!(f, "error"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Note => write!(f, "note"),
+        }
+    }
+}
+
+/// Stable identifiers for every problem the toolchain can report.
+///
+/// Codes never change meaning once published, so tooling can match on them
+/// instead of on message text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorCode {
+    // Lexical errors
+    InvalidCharacter,
+    UnterminatedString,
+    UnclosedInterpolation,
+
+    // Syntax errors
+    UnexpectedToken,
+    ExpectedExpression,
+    InvalidAssignmentTarget,
+
+    // Runtime errors
+    UndefinedVariable,
+    UndefinedFunction,
+    MissingArgument,
+    InvalidOperand,
+    InvalidOperator,
+    DivisionByZero,
+    BreakOutsideLoop,
+    ContinueOutsideLoop,
+    ProduceOutsideFunction,
+    NotIterable,
+    IndexOutOfBounds,
+    KeyNotFound,
+    UndefinedType,
+    MissingField,
+    UnknownField,
+    DuplicateField,
+    TypeMismatch,
+    TooManyArguments,
+    InvalidPattern,
+    UnhandledError,
+    UnknownArgument,
+    DuplicateArgument,
+    IntegerOverflow,
+    NonExhaustiveMatch,
+    ImmutableAssignment,
+    DuplicateDeclaration,
+    InvalidTypeArguments,
+}
+
+impl ErrorCode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorCode::InvalidCharacter => "E0001",
+            ErrorCode::UnterminatedString => "E0002",
+            ErrorCode::UnclosedInterpolation => "E0003",
+            ErrorCode::UnexpectedToken => "E0100",
+            ErrorCode::ExpectedExpression => "E0101",
+            ErrorCode::InvalidAssignmentTarget => "E0102",
+            ErrorCode::UndefinedVariable => "E0200",
+            ErrorCode::UndefinedFunction => "E0201",
+            ErrorCode::MissingArgument => "E0202",
+            ErrorCode::InvalidOperand => "E0203",
+            ErrorCode::InvalidOperator => "E0204",
+            ErrorCode::DivisionByZero => "E0205",
+            ErrorCode::BreakOutsideLoop => "E0206",
+            ErrorCode::ContinueOutsideLoop => "E0207",
+            ErrorCode::ProduceOutsideFunction => "E0208",
+            ErrorCode::NotIterable => "E0209",
+            ErrorCode::IndexOutOfBounds => "E0210",
+            ErrorCode::KeyNotFound => "E0211",
+            ErrorCode::UndefinedType => "E0212",
+            ErrorCode::MissingField => "E0213",
+            ErrorCode::UnknownField => "E0214",
+            ErrorCode::DuplicateField => "E0215",
+            ErrorCode::TypeMismatch => "E0216",
+            ErrorCode::TooManyArguments => "E0217",
+            ErrorCode::InvalidPattern => "E0218",
+            ErrorCode::UnhandledError => "E0219",
+            ErrorCode::UnknownArgument => "E0220",
+            ErrorCode::DuplicateArgument => "E0221",
+            ErrorCode::IntegerOverflow => "E0222",
+            ErrorCode::NonExhaustiveMatch => "E0223",
+            ErrorCode::ImmutableAssignment => "E0224",
+            ErrorCode::DuplicateDeclaration => "E0225",
+            ErrorCode::InvalidTypeArguments => "E0226",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Suggestion {
+    pub message: String,
+    pub span: Span,
+    pub replacement: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{severity}[{code}]: {message}")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub code: ErrorCode,
+    pub message: String,
+    pub primary: Label,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+    pub suggestion: Option<Suggestion>,
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, code: ErrorCode, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            code,
+            message: message.into(),
+            primary: Label {
+                span,
+                message: String::new(),
+            },
+            labels: Vec::new(),
+            notes: Vec::new(),
+            suggestion: None,
+        }
+    }
+
+    pub fn error(code: ErrorCode, message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, code, message, span)
+    }
+
+    pub fn span(&self) -> Span {
+        self.primary.span
+    }
+
+    /// Sets the text printed next to the primary carets.
+    pub fn with_primary_message(mut self, message: impl Into<String>) -> Self {
+        self.primary.message = message.into();
+        self
+    }
+
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    pub fn with_note(mut self, note: impl Into<String>) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    pub fn with_suggestion(
+        mut self,
+        span: Span,
+        replacement: impl Into<String>,
+        message: impl Into<String>,
+    ) -> Self {
+        self.suggestion = Some(Suggestion {
+            message: message.into(),
+            span,
+            replacement: replacement.into(),
+        });
+        self
+    }
+
+    /// Renders the diagnostic as a rustc-style snippet of `source`.
+    ///
+    /// ```text
+    /// error[E0205]: Division by zero.
+    ///  --> script.wg:2:8
+    ///   |
+    /// 2 | write (one / 0)
+    ///   |        ^^^^^^^
+    /// ```
+    pub fn render(&self, source: &str, file_name: &str) -> String {
+        let lines: Vec<&str> = source.split('\n').collect();
+        let mut labels: Vec<(&Label, bool)> = vec![(&self.primary, true)];
+        labels.extend(self.labels.iter().map(|label| (label, false)));
+        labels.sort_by_key(|(label, _)| (label.span.line, label.span.column));
+
+        let max_line = labels.iter().map(|(label, _)| label.span.line).max().unwrap_or(1);
+        let width = max_line.to_string().len();
+        let gutter = " ".repeat(width);
+
+        let mut out = format!("{}\n", self);
+        out.push_str(&format!(
+            "{}--> {}:{}:{}\n",
+            gutter, file_name, self.primary.span.line, self.primary.span.column
+        ));
+        out.push_str(&format!("{} |\n", gutter));
+
+        let mut previous_line: Option<usize> = None;
+        for (label, is_primary) in &labels {
+            let line_number = label.span.line;
+            let Some(text) = line_number.checked_sub(1).and_then(|index| lines.get(index)) else {
+                continue;
+            };
+            let text = text.trim_end_matches('\r');
+
+            if previous_line != Some(line_number) {
+                if matches!(previous_line, Some(previous) if line_number > previous + 1) {
+                    out.push_str("...\n");
+                }
+                out.push_str(&format!("{:>width$} | {}\n", line_number, text, width = width));
+                previous_line = Some(line_number);
+            }
+
+            // Underline up to the end of the span or the end of the line, whichever comes first.
+            let start_column = label.span.column.saturating_sub(1);
+            let available = text.chars().count().saturating_sub(start_column);
+            let span_length = source
+                .get(label.span.start..label.span.end)
+                .map(|s| s.split('\n').next().unwrap_or("").chars().count())
+                .unwrap_or(0);
+            let length = span_length.min(available).max(1);
+            let marker = if *is_primary { "^" } else { "-" };
+
+            out.push_str(&format!(
+                "{} | {}{}",
+                gutter,
+                " ".repeat(start_column),
+                marker.repeat(length)
+            ));
+            if !label.message.is_empty() {
+                out.push(' ');
+                out.push_str(&label.message);
+            }
+            out.push('\n');
+        }
+
+        if !self.notes.is_empty() || self.suggestion.is_some() {
+            out.push_str(&format!("{} |\n", gutter));
+        }
+        for note in &self.notes {
+            out.push_str(&format!("{} = note: {}\n", gutter, note));
+        }
+        if let Some(suggestion) = &self.suggestion {
+            out.push_str(&format!(
+                "{} = help: {}: `{}`\n",
+                gutter, suggestion.message, suggestion.replacement
+            ));
+        }
+
+        out
+    }
+}
+
+/// The candidate spelled most like `name`, if any is close enough to be a
+/// likely typo: at most one edit per three characters.
+pub fn similar_name<'a>(name: &str, candidates: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let limit = (name.chars().count() / 3).max(1);
+    candidates
+        .into_iter()
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= limit)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
+
+/// Levenshtein distance between `a` and `b`, counted in characters.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
@@ -1,129 +1,567 @@
-use crate::parser::{Expr, Literal, Stmt};
-use std::collections::HashMap;
-use crate::lexer::Token;
+use crate::parser::{Expr, ExprKind, Literal, OfCase, Parser, Stmt, StmtKind, Type, TypeDefinition};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::rc::Rc;
+use crate::diagnostic::{similar_name, Diagnostic, ErrorCode};
+use crate::lexer::{Span, Token};
 
 #[derive(Debug, Clone)]
 pub enum Value {
     Number(f64),
+    /// Exact whole numbers; arithmetic that leaves the `i64` range is an error.
+    Integer(i64),
     String(String),
     Boolean(bool),
+    List(Vec<Value>),
+    /// A fixed number of values, written `(a, b)`.
+    Tuple(Vec<Value>),
+    /// Entries in insertion order.
+    Map(Vec<(Value, Value)>),
+    /// `start..end` counts up from `start` and stops before `end`.
+    Range {
+        start: i64,
+        end: i64,
+    },
     Function {
         name: String,
-        params: Vec<(String, String)>,
+        params: Vec<(String, Type)>,
+        return_type: Type,
         body: Vec<Stmt>,
+        closure: Rc<RefCell<Environment>>,
+    },
+    Module {
+        name: String,
+        environment: Rc<RefCell<Environment>>,
+    },
+    /// Fields are kept in the order the record type declares them.
+    Record {
+        type_name: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// A declared record type. `Name { ... }` looks this up to build a record.
+    RecordType {
+        name: String,
+        fields: Vec<(String, Type)>,
+    },
+    /// One case of a variant type, such as `Circle(5)`, with its named payload.
+    Variant {
+        type_name: String,
+        tag: String,
+        fields: Vec<(String, Value)>,
+    },
+    /// Calling this builds a `Variant` with the given tag.
+    VariantConstructor {
+        type_name: String,
+        tag: String,
+        fields: Vec<(String, Type)>,
     },
     Nil,
 }
 
+/// How a value reads in program output and string interpolation: the way it
+/// would be written in source, except that top-level text is not quoted.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{}", n),
+            Value::Integer(i) => write!(f, "{}", i),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Boolean(true) => write!(f, "yes"),
+            Value::Boolean(false) => write!(f, "no"),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Tuple(items) => {
+                write!(f, "(")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, item)?;
+                }
+                write!(f, ")")
+            }
+            Value::Map(entries) if entries.is_empty() => write!(f, "{{}}"),
+            Value::Map(entries) => {
+                write!(f, "{{ ")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, key)?;
+                    write!(f, ": ")?;
+                    write_nested(f, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::Range { start, end } => write!(f, "{}..{}", start, end),
+            Value::Function { name, .. } => write!(f, "<function {}>", name),
+            Value::Module { name, .. } => write!(f, "<module {}>", name),
+            Value::Record { type_name, fields } => {
+                write!(f, "{} {{ ", type_name)?;
+                for (i, (name, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}: ", name)?;
+                    write_nested(f, value)?;
+                }
+                write!(f, " }}")
+            }
+            Value::RecordType { name, .. } => write!(f, "#{}", name),
+            Value::Variant { tag, fields, .. } if fields.is_empty() => write!(f, "{}", tag),
+            Value::Variant { tag, fields, .. } => {
+                write!(f, "{}(", tag)?;
+                for (i, (_, value)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_nested(f, value)?;
+                }
+                write!(f, ")")
+            }
+            Value::VariantConstructor { tag, .. } => write!(f, "<constructor {}>", tag),
+            Value::Nil => write!(f, "nothing"),
+        }
+    }
+}
+
+/// Text inside a collection, record or variant keeps its quotes.
+fn write_nested(f: &mut fmt::Formatter<'_>, value: &Value) -> fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "{:?}", s),
+        other => write!(f, "{}", other),
+    }
+}
+
+/// Two numeric operands. Integers stay exact; mixing in a `#Number` makes
+/// both sides floating point.
+enum Operands {
+    Integers(i64, i64),
+    Numbers(f64, f64),
+}
+
+fn numeric_operands(left: Value, right: Value) -> Option<Operands> {
+    match (left, right) {
+        (Value::Integer(l), Value::Integer(r)) => Some(Operands::Integers(l, r)),
+        (Value::Integer(l), Value::Number(r)) => Some(Operands::Numbers(l as f64, r)),
+        (Value::Number(l), Value::Integer(r)) => Some(Operands::Numbers(l, r as f64)),
+        (Value::Number(l), Value::Number(r)) => Some(Operands::Numbers(l, r)),
+        _ => None,
+    }
+}
+
+/// The result of checked integer arithmetic, or an overflow error.
+fn checked(result: Option<i64>, span: Span) -> Result<Value, Diagnostic> {
+    result.map(Value::Integer).ok_or_else(|| {
+        Diagnostic::error(ErrorCode::IntegerOverflow, "Integer arithmetic overflowed.", span)
+            .with_note("#Integer values range from -9223372036854775808 to 9223372036854775807")
+    })
+}
+
+/// Widens an integer stored where a `#Number` is declared, including inside
+/// collections and tuples.
+fn conform(value: Value, ty: &Type) -> Value {
+    match (value, ty) {
+        (Value::Integer(i), Type::Primitive(name)) if name == "Number" => Value::Number(i as f64),
+        (Value::List(values), Type::List(element)) => {
+            Value::List(values.into_iter().map(|value| conform(value, element)).collect())
+        }
+        (Value::Map(entries), Type::Map(key, value)) => Value::Map(
+            entries
+                .into_iter()
+                .map(|(k, v)| (conform(k, key), conform(v, value)))
+                .collect(),
+        ),
+        (Value::Tuple(values), Type::Tuple(types)) if values.len() == types.len() => {
+            Value::Tuple(values.into_iter().zip(types).map(|(value, ty)| conform(value, ty)).collect())
+        }
+        (other, _) => other,
+    }
+}
+
+/// Whether `value` may be stored where `ty` is declared. Records and variants
+/// must be of the named type, ignoring type arguments; aliases and other named
+/// types are not checked here.
+fn fits(value: &Value, ty: &Type) -> bool {
+    match ty {
+        Type::Primitive(name) => match name.as_str() {
+            "Number" => matches!(value, Value::Number(_) | Value::Integer(_)),
+            "Integer" => matches!(value, Value::Integer(_)),
+            "Text" => matches!(value, Value::String(_)),
+            "Decision" => matches!(value, Value::Boolean(_)),
+            "Nothing" | "Bliss" => matches!(value, Value::Nil),
+            _ => true,
+        },
+        Type::List(_) => matches!(value, Value::List(_)),
+        Type::Map(_, _) => matches!(value, Value::Map(_)),
+        Type::Function { .. } => matches!(value, Value::Function { .. } | Value::VariantConstructor { .. }),
+        Type::Union(options) => options.iter().any(|option| fits(value, option)),
+        Type::Custom(name) | Type::Generic { name, .. } => match value {
+            Value::Record { type_name, .. } | Value::Variant { type_name, .. } => type_name == name,
+            _ => true,
+        },
+        Type::Tuple(types) => match value {
+            Value::Tuple(values) => {
+                values.len() == types.len() && values.iter().zip(types).all(|(value, ty)| fits(value, ty))
+            }
+            _ => false,
+        },
+    }
+}
+
+/// Why evaluation left a block early. Errors and `produce`/`break`/`continue`
+/// all unwind through nested blocks the same way until something handles them.
+#[derive(Debug)]
+pub enum Signal {
+    Error(Diagnostic),
+    Produce(Value, Span),
+    Break(Span),
+    Continue(Span),
+    /// `try` met an `Error` and is returning it from the enclosing function.
+    Propagate(Value, Span),
+}
+
+impl From<Diagnostic> for Signal {
+    fn from(diagnostic: Diagnostic) -> Self {
+        Signal::Error(diagnostic)
+    }
+}
+
+/// A single scope. Lookups that miss fall through to the enclosing scope,
+/// so blocks, function bodies and modules all see the names around them.
+/// Names are constants unless declared with `forNow`.
+#[derive(Default)]
 pub struct Environment {
     values: HashMap<String, Value>,
+    mutable: HashSet<String>,
+    /// Declared types of the names that have one; changes conform to them.
+    types: HashMap<String, Type>,
+    enclosing: Option<Rc<RefCell<Environment>>>,
 }
 
 impl Environment {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_enclosing(enclosing: Rc<RefCell<Environment>>) -> Self {
         Self {
             values: HashMap::new(),
+            mutable: HashSet::new(),
+            types: HashMap::new(),
+            enclosing: Some(enclosing),
         }
     }
 
+    /// Binds `name` as a constant, replacing any binding already in this scope.
     pub fn define(&mut self, name: String, value: Value) {
+        self.mutable.remove(&name);
+        self.types.remove(&name);
         self.values.insert(name, value);
     }
 
+    /// Binds a name the program declares itself. Each name can be declared
+    /// once per scope, though inner scopes may shadow it.
+    pub fn declare(&mut self, name: String, value: Value, mutable: bool, span: Span) -> Result<(), Diagnostic> {
+        if self.values.contains_key(&name) {
+            return Err(Diagnostic::error(
+                ErrorCode::DuplicateDeclaration,
+                format!("'{}' is already declared in this scope.", name),
+                span,
+            ));
+        }
+        if mutable {
+            self.mutable.insert(name.clone());
+        }
+        self.values.insert(name, value);
+        Ok(())
+    }
+
+    /// Declares a value with its declared type, such as `count #Number is 1`.
+    pub fn declare_typed(
+        &mut self,
+        name: String,
+        value: Value,
+        value_type: Type,
+        mutable: bool,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        self.declare(name.clone(), conform(value, &value_type), mutable, span)?;
+        self.types.insert(name, value_type);
+        Ok(())
+    }
+
     pub fn get(&self, name: &str) -> Option<Value> {
+        match self.values.get(name) {
+            Some(value) => Some(value.clone()),
+            None => self.enclosing.as_ref()?.borrow().get(name),
+        }
+    }
+
+    /// Every name visible from this scope, innermost first.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.values.keys().cloned().collect();
+        if let Some(enclosing) = &self.enclosing {
+            names.extend(enclosing.borrow().names());
+        }
+        names
+    }
+
+    /// Looks `name` up in this scope only, ignoring enclosing scopes.
+    pub fn get_local(&self, name: &str) -> Option<Value> {
         self.values.get(name).cloned()
     }
 
-    pub fn assign(&mut self, name: &str, value: Value) -> Result<(), String> {
-        if self.values.contains_key(name) {
-            self.values.insert(name.to_string(), value);
+    pub fn assign(&mut self, name: &str, value: Value, span: Span) -> Result<(), Diagnostic> {
+        if let Some(slot) = self.values.get_mut(name) {
+            if !self.mutable.contains(name) {
+                return Err(Diagnostic::error(
+                    ErrorCode::ImmutableAssignment,
+                    format!("Cannot change '{}' because it is a constant.", name),
+                    span,
+                )
+                .with_note(format!("declare it with 'forNow {}' to be able to change it", name)));
+            }
+            *slot = match self.types.get(name) {
+                Some(value_type) => conform(value, value_type),
+                None => value,
+            };
             Ok(())
+        } else if let Some(enclosing) = &self.enclosing {
+            enclosing.borrow_mut().assign(name, value, span)
         } else {
-            Err(format!("Undefined variable '{}'.", name))
+            Err(Diagnostic::error(
+                ErrorCode::UndefinedVariable,
+                format!("Undefined variable '{}'.", name),
+                span,
+            ))
         }
     }
 }
 
+// Functions hold on to the scope they were declared in, and that scope holds
+// the function, so printing a scope's values would never terminate.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut names: Vec<&String> = self.values.keys().collect();
+        names.sort();
+        f.debug_struct("Environment")
+            .field("names", &names)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Types every program can use without declaring them.
+pub(crate) const PRELUDE: &str = "
+see #Result(T, E = Any) is #Variant {
+  Success(value #T)
+  Error(message #E)
+}
+see #Optional(T) is #Variant {
+  Some(value #T)
+  None
+}
+see #RuntimeError is #Record {
+  message #Text
+  kind #Text
+  code #Text
+  line #Integer
+  column #Integer
+}
+";
+
 pub struct Interpreter {
-    environment: Environment,
+    environment: Rc<RefCell<Environment>>,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Interpreter {
     pub fn new() -> Self {
-        Self {
-            environment: Environment::new(),
-        }
+        let prelude = Rc::new(RefCell::new(Environment::new()));
+        let mut interpreter = Self { environment: Rc::clone(&prelude) };
+        let (statements, _) = Parser::new(PRELUDE).parse();
+        interpreter
+            .interpret(statements)
+            .expect("the prelude always evaluates");
+
+        // Programs run in their own scope so they can shadow prelude names
+        interpreter.environment = Rc::new(RefCell::new(Environment::with_enclosing(prelude)));
+        interpreter
     }
 
-    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<Value, String> {
+    pub fn interpret(&mut self, statements: Vec<Stmt>) -> Result<Value, Diagnostic> {
+        self.run(statements).map_err(Self::unhandled)
+    }
+
+    /// Runs a block's statements in the current environment and yields the
+    /// value of the last one. Functions and types are declared before any
+    /// statement runs, so they can be used above the line that declares them.
+    fn run(&mut self, statements: Vec<Stmt>) -> Result<Value, Signal> {
+        let mut rest = Vec::with_capacity(statements.len());
+        for stmt in statements {
+            if matches!(stmt.kind, StmtKind::Function { .. } | StmtKind::TypeDefinition { .. }) {
+                self.execute(stmt)?;
+                rest.push(None);
+            } else {
+                rest.push(Some(stmt));
+            }
+        }
         let mut last_value = Value::Nil;
-        for statement in statements {
-            last_value = self.execute(statement)?;
+        for stmt in rest {
+            last_value = match stmt {
+                Some(stmt) => self.execute(stmt)?,
+                None => Value::Nil,
+            };
         }
         Ok(last_value)
     }
 
-    fn execute(&mut self, stmt: Stmt) -> Result<Value, String> {
-        match stmt {
-            Stmt::Expression(expr) => self.evaluate(expr),
-            Stmt::Function { name, return_type: _, params, body } => {
+    /// Turns a signal that escaped everything able to handle it into an error.
+    fn unhandled(signal: Signal) -> Diagnostic {
+        match signal {
+            Signal::Break(span) => Diagnostic::error(
+                ErrorCode::BreakOutsideLoop,
+                "Cannot use 'break' outside of a loop.",
+                span,
+            ),
+            Signal::Continue(span) => Diagnostic::error(
+                ErrorCode::ContinueOutsideLoop,
+                "Cannot use 'continue' outside of a loop.",
+                span,
+            ),
+            Signal::Error(diagnostic) => diagnostic,
+            Signal::Produce(_, span) => Diagnostic::error(
+                ErrorCode::ProduceOutsideFunction,
+                "Cannot use 'produce' outside of a function.",
+                span,
+            ),
+            Signal::Propagate(error, span) => Diagnostic::error(
+                ErrorCode::UnhandledError,
+                format!("'try' failed outside of a function: {}", error),
+                span,
+            ),
+        }
+    }
+
+    /// The `RuntimeError` record a `catch` block sees for `diagnostic`.
+    fn runtime_error(diagnostic: &Diagnostic) -> Value {
+        let span = diagnostic.span();
+        Value::Record {
+            type_name: "RuntimeError".to_string(),
+            fields: vec![
+                ("message".to_string(), Value::String(diagnostic.message.clone())),
+                ("kind".to_string(), Value::String(format!("{:?}", diagnostic.code))),
+                ("code".to_string(), Value::String(diagnostic.code.to_string())),
+                ("line".to_string(), Value::Integer(span.line as i64)),
+                ("column".to_string(), Value::Integer(span.column as i64)),
+            ],
+        }
+    }
+
+    fn execute(&mut self, stmt: Stmt) -> Result<Value, Signal> {
+        let span = stmt.span;
+        match stmt.kind {
+            StmtKind::Expression(expr) => self.evaluate(expr),
+            StmtKind::Function { name, return_type, params, body } => {
                 // Store the function definition
                 let function_value = Value::Function {
                     name: name.clone(),
                     params,
+                    return_type,
                     body,
+                    closure: Rc::clone(&self.environment),
                 };
-                self.environment.define(name, function_value);
+                self.environment.borrow_mut().declare(name, function_value, false, span)?;
                 Ok(Value::Nil)
             }
-            Stmt::Value { name, type_name: _, initializer, mutable: _ } => {
+            StmtKind::Value { name, value_type, initializer, mutable } => {
                 let value = self.evaluate(initializer)?;
-                self.environment.define(name, value.clone());
-                Ok(value)
+                let mut environment = self.environment.borrow_mut();
+                environment.declare_typed(name.clone(), value, value_type, mutable, span)?;
+                Ok(environment.get_local(&name).unwrap_or(Value::Nil))
             }
-            Stmt::Change { name, value } => {
+            StmtKind::Destructure { names, initializer, mutable } => {
+                let initializer_span = initializer.span;
+                let values = match self.evaluate(initializer)? {
+                    Value::Tuple(values) if values.len() == names.len() => values,
+                    other => {
+                        return Err(Diagnostic::error(
+                            ErrorCode::TypeMismatch,
+                            format!("Cannot unpack {} into {} names.", other, names.len()),
+                            initializer_span,
+                        ).into());
+                    }
+                };
+                let mut environment = self.environment.borrow_mut();
+                for (name, value) in names.into_iter().zip(values) {
+                    environment.declare(name, value, mutable, span)?;
+                }
+                Ok(Value::Nil)
+            }
+            StmtKind::Change { name, indices, value } => {
                 let evaluated_value = self.evaluate(value)?;
-                self.environment.assign(&name, evaluated_value.clone())?;
+                let new_value = if indices.is_empty() {
+                    evaluated_value.clone()
+                } else {
+                    let current = self.environment.borrow().get(&name).ok_or_else(|| {
+                        Diagnostic::error(
+                            ErrorCode::UndefinedVariable,
+                            format!("Undefined variable '{}'.", name),
+                            span,
+                        )
+                    })?;
+                    let mut keys = Vec::with_capacity(indices.len());
+                    for index in indices {
+                        let index_span = index.span;
+                        keys.push((self.evaluate(index)?, index_span));
+                    }
+                    self.set_index(current, &keys, evaluated_value.clone())?
+                };
+                self.environment.borrow_mut().assign(&name, new_value, span)?;
                 Ok(evaluated_value)
             }
-            Stmt::Produce(value) => {
-                if let Some(expr) = value {
-                    self.evaluate(expr)
-                } else {
-                    Ok(Value::Nil)
-                }
+            StmtKind::Produce(value) => {
+                let value = match value {
+                    Some(expr) => self.evaluate(expr)?,
+                    None => Value::Nil,
+                };
+                Err(Signal::Produce(value, span))
             }
-            Stmt::If { condition, then_branch, else_branch } => {
-                let condition_value = self.evaluate(condition)?;
-                if self.is_truthy(condition_value) {
-                    let mut result = Value::Nil;
-                    for stmt in then_branch {
-                        result = self.execute(stmt)?;
-                    }
-                    Ok(result)
+            StmtKind::Break => Err(Signal::Break(span)),
+            StmtKind::Continue => Err(Signal::Continue(span)),
+            StmtKind::If { condition, then_branch, else_branch } => {
+                let scope = self.child_scope();
+                if self.condition_holds(condition, &scope)? {
+                    self.execute_block(then_branch, scope)
                 } else if let Some(else_stmts) = else_branch {
-                    let mut result = Value::Nil;
-                    for stmt in else_stmts {
-                        result = self.execute(stmt)?;
-                    }
-                    Ok(result)
+                    self.execute_block(else_stmts, self.child_scope())
                 } else {
                     Ok(Value::Nil)
                 }
             }
-            Stmt::Unless { condition, body } => {
+            StmtKind::Unless { condition, body } => {
                 let condition_value = self.evaluate(condition)?;
                 if !self.is_truthy(condition_value) {
-                    let mut result = Value::Nil;
-                    for stmt in body {
-                        result = self.execute(stmt)?;
-                    }
-                    Ok(result)
+                    self.execute_block(body, self.child_scope())
                 } else {
                     Ok(Value::Nil)
                 }
             }
-            Stmt::While { condition, body } => {
+            StmtKind::While { condition, body } => {
                 let mut result = Value::Nil;
                 let condition = condition.clone();
                 let body = body.clone();
@@ -132,193 +570,950 @@ impl Interpreter {
                 let mut cond_result = self.evaluate(condition.clone())?;
                 
                 while self.is_truthy(cond_result) {
-                    for stmt in body.clone() {
-                        result = self.execute(stmt)?;
+                    match self.execute_block(body.clone(), self.child_scope()) {
+                        Ok(value) => result = value,
+                        Err(Signal::Break(_)) => break,
+                        Err(Signal::Continue(_)) => {}
+                        Err(signal) => return Err(signal),
                     }
                     // Re-evaluate condition after each loop iteration
                     cond_result = self.evaluate(condition.clone())?;
                 }
                 Ok(result)
             }
-            Stmt::Write(expr) => {
-                let value = self.evaluate(expr)?;
-                let display_value = match &value {
-                    Value::String(s) => s.clone(),
-                    _ => format!("{:?}", value)
+            StmtKind::For { variable, iterable, body } => {
+                let iterable_span = iterable.span;
+                let iterable = self.evaluate(iterable)?;
+                let mut result = Value::Nil;
+                
+                for item in self.iterate(iterable, iterable_span)? {
+                    // Every iteration gets its own binding of the loop variable
+                    let mut scope = Environment::with_enclosing(Rc::clone(&self.environment));
+                    scope.define(variable.clone(), item);
+                    match self.execute_block(body.clone(), Rc::new(RefCell::new(scope))) {
+                        Ok(value) => result = value,
+                        Err(Signal::Break(_)) => break,
+                        Err(Signal::Continue(_)) => {}
+                        Err(signal) => return Err(signal),
+                    }
+                }
+                Ok(result)
+            }
+            StmtKind::Of { value, cases, default } => self.evaluate_of(value, cases, default),
+            StmtKind::TypeDefinition { name, parameters, definition } => {
+                // Type parameters are only checked statically; at runtime they hold anything
+                let erased: HashMap<String, Type> = parameters
+                    .into_iter()
+                    .map(|(parameter, _)| (parameter, Type::Primitive("Any".to_string())))
+                    .collect();
+                let erase = |fields: Vec<(String, Type)>| -> Vec<(String, Type)> {
+                    fields.into_iter().map(|(field, ty)| (field, ty.substitute(&erased))).collect()
                 };
-                println!("{}", display_value);
+                match definition {
+                    TypeDefinition::Record { fields } => {
+                        let record_type = Value::RecordType { name: name.clone(), fields: erase(fields) };
+                        self.environment.borrow_mut().declare(name, record_type, false, span)?;
+                    }
+                    TypeDefinition::Variant { variants } => {
+                        let mut environment = self.environment.borrow_mut();
+                        for (tag, fields) in variants {
+                            // Cases without a payload are values rather than constructors
+                            let value = if fields.is_empty() {
+                                Value::Variant { type_name: name.clone(), tag: tag.clone(), fields: Vec::new() }
+                            } else {
+                                Value::VariantConstructor { type_name: name.clone(), tag: tag.clone(), fields: erase(fields) }
+                            };
+                            environment.declare(tag, value, false, span)?;
+                        }
+                    }
+                    TypeDefinition::Alias(_) => {}
+                }
+                Ok(Value::Nil)
+            }
+            StmtKind::Write(expr) => {
+                let value = self.evaluate(expr)?;
+                println!("{}", value);
+                Ok(Value::Nil)
+            }
+            StmtKind::Rescue { body, error_name, handler } => {
+                match self.execute_block(body, self.child_scope()) {
+                    // Only failures are caught; `produce`, `break` and friends keep unwinding
+                    Err(Signal::Error(diagnostic)) => {
+                        let scope = self.child_scope();
+                        scope.borrow_mut().define(error_name, Self::runtime_error(&diagnostic));
+                        self.execute_block(handler, scope)
+                    }
+                    result => result,
+                }
+            }
+            // Modules are already reachable by name, so an import has nothing to bind
+            StmtKind::Import { .. } => Ok(Value::Nil),
+            StmtKind::ModuleDeclaration { name, body } => {
+                let environment = self.child_scope();
+                self.execute_block(body, Rc::clone(&environment))?;
+                let module = Value::Module { name: name.clone(), environment };
+                self.environment.borrow_mut().declare(name, module, false, span)?;
                 Ok(Value::Nil)
             }
-            // Add placeholder implementations for other statement types
-            _ => Ok(Value::Nil),
         }
     }
 
-    fn evaluate(&mut self, expr: Expr) -> Result<Value, String> {
-        match expr {
-            Expr::Binary { left, operator, right } => {
+    /// Runs `statements` inside `environment`, restoring the current scope
+    /// afterwards even if one of them fails.
+    fn execute_block(
+        &mut self,
+        statements: Vec<Stmt>,
+        environment: Rc<RefCell<Environment>>,
+    ) -> Result<Value, Signal> {
+        let previous = std::mem::replace(&mut self.environment, environment);
+        let result = self.run(statements);
+        self.environment = previous;
+        result
+    }
+
+    /// Runs the first arm with a pattern matching `subject`, or the `otherwise`
+    /// arm if none match, and yields whatever that arm produced.
+    fn evaluate_of(
+        &mut self,
+        subject: Expr,
+        cases: Vec<OfCase>,
+        default: Option<Vec<Stmt>>,
+    ) -> Result<Value, Signal> {
+        let subject = self.evaluate(subject)?;
+        for (patterns, body) in cases {
+            for pattern in patterns {
+                if let Some(bindings) = self.match_pattern(&subject, pattern)? {
+                    let scope = self.child_scope();
+                    for (name, value) in bindings {
+                        scope.borrow_mut().define(name, value);
+                    }
+                    return self.execute_block(body, scope);
+                }
+            }
+        }
+        match default {
+            Some(body) => self.execute_block(body, self.child_scope()),
+            None => Ok(Value::Nil),
+        }
+    }
+
+    /// The values a `for` loop visits: list elements, the whole numbers of a
+    /// range, map entries as `(key, value)` tuples, or the characters of a text.
+    fn iterate(&self, value: Value, span: Span) -> Result<Box<dyn Iterator<Item = Value>>, Diagnostic> {
+        match value {
+            Value::List(elements) => Ok(Box::new(elements.into_iter())),
+            Value::Range { start, end } => Ok(Box::new((start..end).map(Value::Integer))),
+            Value::Map(entries) => Ok(Box::new(
+                entries.into_iter().map(|(key, value)| Value::Tuple(vec![key, value])),
+            )),
+            Value::String(text) => Ok(Box::new(
+                text.chars().map(|c| Value::String(c.to_string())).collect::<Vec<_>>().into_iter(),
+            )),
+            other => Err(Diagnostic::error(
+                ErrorCode::NotIterable,
+                format!("Cannot iterate over {}.", other),
+                span,
+            )),
+        }
+    }
+
+    /// Evaluates `name: value` pairs, rejecting names given more than once.
+    fn evaluate_fields(&mut self, fields: Vec<(String, Expr)>) -> Result<Vec<(String, Value, Span)>, Signal> {
+        let mut values: Vec<(String, Value, Span)> = Vec::with_capacity(fields.len());
+        for (name, expr) in fields {
+            let field_span = expr.span;
+            if values.iter().any(|(field, _, _)| *field == name) {
+                return Err(Diagnostic::error(
+                    ErrorCode::DuplicateField,
+                    format!("Field '{}' is given more than once.", name),
+                    field_span,
+                ).into());
+            }
+            let value = self.evaluate(expr)?;
+            values.push((name, value, field_span));
+        }
+        Ok(values)
+    }
+
+    fn check_field_type(
+        &self,
+        owner: &str,
+        field: &str,
+        field_type: &Type,
+        value: &Value,
+        span: Span,
+    ) -> Result<(), Diagnostic> {
+        if fits(value, field_type) {
+            Ok(())
+        } else {
+            Err(Diagnostic::error(
+                ErrorCode::TypeMismatch,
+                format!("Field '{}' of '{}' cannot hold {}.", field, owner, value),
+                span,
+            ))
+        }
+    }
+
+    /// Calls a function or variant constructor with already evaluated arguments.
+    fn call_value(
+        &mut self,
+        callee: Value,
+        arguments: Vec<(Value, Span)>,
+        named_arguments: Vec<(String, Value, Span)>,
+        span: Span,
+    ) -> Result<Value, Signal> {
+        match callee {
+            Value::Function { name, params, return_type, body, closure } => {
+                let values = self.bind_arguments(&name, &params, arguments, named_arguments, span)?;
+                
+                // The call gets a fresh scope inside the one the function was declared in
+                let mut function_env = Environment::with_enclosing(closure);
+                for ((param_name, param_type), (value, _)) in params.into_iter().zip(values) {
+                    function_env.define(param_name, conform(value, &param_type));
+                }
+                
+                // Execute the function body; `produce` and a failed `try` end it early
+                match self.execute_block(body, Rc::new(RefCell::new(function_env))) {
+                    Ok(value) | Err(Signal::Produce(value, _)) => Ok(conform(value, &return_type)),
+                    Err(Signal::Propagate(value, _)) => Ok(value),
+                    Err(signal @ (Signal::Break(_) | Signal::Continue(_))) => {
+                        Err(Self::unhandled(signal).into())
+                    }
+                    Err(signal) => Err(signal),
+                }
+            }
+            Value::VariantConstructor { type_name, tag, fields } => {
+                let values = self.bind_arguments(&tag, &fields, arguments, named_arguments, span)?;
+                let mut payload = Vec::with_capacity(fields.len());
+                for ((field, field_type), (value, arg_span)) in fields.into_iter().zip(values) {
+                    self.check_field_type(&tag, &field, &field_type, &value, arg_span)?;
+                    payload.push((field.clone(), conform(value, &field_type)));
+                }
+                Ok(Value::Variant { type_name, tag, fields: payload })
+            }
+            other => Err(Diagnostic::error(
+                ErrorCode::InvalidOperand,
+                format!("Cannot call {}.", other),
+                span,
+            ).into()),
+        }
+    }
+
+    /// Lines arguments up with `params`. Positional arguments fill parameters
+    /// from the left, then named arguments fill the rest by name.
+    fn bind_arguments(
+        &self,
+        callee: &str,
+        params: &[(String, Type)],
+        arguments: Vec<(Value, Span)>,
+        named_arguments: Vec<(String, Value, Span)>,
+        span: Span,
+    ) -> Result<Vec<(Value, Span)>, Diagnostic> {
+        if arguments.len() > params.len() {
+            let extra = arguments[params.len()].1;
+            return Err(Diagnostic::error(
+                ErrorCode::TooManyArguments,
+                format!("'{}' takes {} argument(s) but {} were given.", callee, params.len(), arguments.len()),
+                extra,
+            ));
+        }
+        
+        let mut slots: Vec<Option<(Value, Span)>> = arguments.into_iter().map(Some).collect();
+        slots.resize(params.len(), None);
+        for (name, value, arg_span) in named_arguments {
+            let Some(position) = params.iter().position(|(param, _)| *param == name) else {
+                return Err(Diagnostic::error(
+                    ErrorCode::UnknownArgument,
+                    format!("'{}' has no parameter named '{}'.", callee, name),
+                    arg_span,
+                ));
+            };
+            if slots[position].is_some() {
+                return Err(Diagnostic::error(
+                    ErrorCode::DuplicateArgument,
+                    format!("Argument '{}' is given more than once.", name),
+                    arg_span,
+                ));
+            }
+            slots[position] = Some((value, arg_span));
+        }
+        
+        params
+            .iter()
+            .zip(slots)
+            .map(|((param, _), slot)| {
+                slot.ok_or_else(|| Diagnostic::error(
+                    ErrorCode::MissingArgument,
+                    format!("Missing argument for parameter '{}' of '{}'.", param, callee),
+                    span,
+                ))
+            })
+            .collect()
+    }
+
+    /// Decides an `if` condition. `subject = Ctor(a, b)` tests the variant tag
+    /// and, when it matches, binds the payload fields into `scope`; conditions
+    /// joined with `and` can each bind.
+    fn condition_holds(&mut self, condition: Expr, scope: &Rc<RefCell<Environment>>) -> Result<bool, Signal> {
+        if let ExprKind::Logical { left, operator: Token::And, right } = condition.kind {
+            return Ok(self.condition_holds(*left, scope)? && self.condition_holds(*right, scope)?);
+        }
+        if let ExprKind::Binary { left, operator: Token::Equal, right } = &condition.kind {
+            if self.is_constructor_pattern(right) {
+                let subject = self.evaluate(*left.clone())?;
+                return match self.match_pattern(&subject, *right.clone())? {
+                    Some(bindings) => {
+                        for (name, value) in bindings {
+                            scope.borrow_mut().define(name, value);
+                        }
+                        Ok(true)
+                    }
+                    None => Ok(false),
+                };
+            }
+        }
+        let value = self.evaluate(condition)?;
+        Ok(self.is_truthy(value))
+    }
+
+    fn is_constructor_pattern(&self, expr: &Expr) -> bool {
+        self.constructor_call(expr).is_some()
+    }
+
+    /// If `expr` is `Name(...)` and `Name` is a variant constructor, returns
+    /// the constructor and the call's arguments.
+    fn constructor_call<'a>(&self, expr: &'a Expr) -> Option<(Value, &'a [Expr])> {
+        let ExprKind::FunctionCall { callee, arguments, .. } = &expr.kind else {
+            return None;
+        };
+        let ExprKind::Variable(name) = &callee.kind else {
+            return None;
+        };
+        match self.environment.borrow().get(name) {
+            Some(constructor @ Value::VariantConstructor { .. }) => Some((constructor, arguments)),
+            _ => None,
+        }
+    }
+
+    /// Matches `subject` against a pattern, returning the names it binds, or
+    /// `None` if it does not match.
+    ///
+    /// `Ctor(a, b)` matches a variant with that tag; its arguments are matched
+    /// against the payload in order, where lowercase names bind and anything
+    /// else is a nested pattern. Any other pattern is evaluated and compared
+    /// with `=`.
+    fn match_pattern(&mut self, subject: &Value, pattern: Expr) -> Result<Option<Vec<(String, Value)>>, Signal> {
+        let span = pattern.span;
+        if let Some((Value::VariantConstructor { type_name, tag, fields }, arguments)) = self.constructor_call(&pattern) {
+            if arguments.len() != fields.len() {
+                return Err(Diagnostic::error(
+                    ErrorCode::InvalidPattern,
+                    format!("Pattern '{}' needs {} field(s) but names {}.", tag, fields.len(), arguments.len()),
+                    span,
+                ).into());
+            }
+            let values = match subject {
+                Value::Variant { type_name: subject_type, tag: subject_tag, fields: values }
+                    if *subject_type == type_name && *subject_tag == tag => values,
+                _ => return Ok(None),
+            };
+            let mut bindings = Vec::new();
+            for (argument, (_, value)) in arguments.iter().zip(values) {
+                match &argument.kind {
+                    ExprKind::Variable(binding) if !binding.starts_with(|c: char| c.is_ascii_uppercase()) => {
+                        bindings.push((binding.clone(), value.clone()));
+                    }
+                    _ => match self.match_pattern(value, argument.clone())? {
+                        Some(nested) => bindings.extend(nested),
+                        None => return Ok(None),
+                    },
+                }
+            }
+            return Ok(Some(bindings));
+        }
+        let candidate = self.evaluate(pattern)?;
+        Ok(self.is_equal(subject.clone(), candidate).then(Vec::new))
+    }
+
+    /// Looks up `index` in a list, tuple, map or text. Ranges slice lists and text.
+    fn index(&self, object: Value, index: Value, span: Span) -> Result<Value, Diagnostic> {
+        match (object, index) {
+            (Value::List(elements), Value::Range { start, end }) => {
+                let (start, end) = self.slice_bounds(start, end, elements.len(), span)?;
+                Ok(Value::List(elements[start..end].to_vec()))
+            }
+            (Value::List(elements) | Value::Tuple(elements), index) => {
+                let position = self.position(index, elements.len(), span)?;
+                Ok(elements[position].clone())
+            }
+            (Value::String(text), Value::Range { start, end }) => {
+                let chars: Vec<char> = text.chars().collect();
+                let (start, end) = self.slice_bounds(start, end, chars.len(), span)?;
+                Ok(Value::String(chars[start..end].iter().collect()))
+            }
+            (Value::String(text), index) => {
+                let chars: Vec<char> = text.chars().collect();
+                let position = self.position(index, chars.len(), span)?;
+                Ok(Value::String(chars[position].to_string()))
+            }
+            (Value::Map(entries), key) => entries
+                .into_iter()
+                .find(|(existing, _)| self.is_equal(existing.clone(), key.clone()))
+                .map(|(_, value)| value)
+                .ok_or_else(|| Diagnostic::error(
+                    ErrorCode::KeyNotFound,
+                    format!("Key {} is not in the map.", key),
+                    span,
+                )),
+            (object, _) => Err(Diagnostic::error(
+                ErrorCode::InvalidOperand,
+                format!("Cannot index into {}.", object),
+                span,
+            )),
+        }
+    }
+
+    /// Replaces the element at the end of `keys` inside `object`, returning the
+    /// updated copy. Map keys that are missing are added; list positions must exist.
+    fn set_index(&self, object: Value, keys: &[(Value, Span)], value: Value) -> Result<Value, Diagnostic> {
+        let Some(((key, span), rest)) = keys.split_first() else {
+            return Ok(value);
+        };
+        match object {
+            Value::List(mut elements) => {
+                let position = self.position(key.clone(), elements.len(), *span)?;
+                let element = std::mem::replace(&mut elements[position], Value::Nil);
+                elements[position] = self.set_index(element, rest, value)?;
+                Ok(Value::List(elements))
+            }
+            Value::Map(mut entries) => {
+                let existing = entries
+                    .iter()
+                    .position(|(existing, _)| self.is_equal(existing.clone(), key.clone()));
+                match existing {
+                    Some(position) => {
+                        let entry = std::mem::replace(&mut entries[position].1, Value::Nil);
+                        entries[position].1 = self.set_index(entry, rest, value)?;
+                    }
+                    None if rest.is_empty() => entries.push((key.clone(), value)),
+                    None => {
+                        return Err(Diagnostic::error(
+                            ErrorCode::KeyNotFound,
+                            format!("Key {} is not in the map.", key),
+                            *span,
+                        ))
+                    }
+                }
+                Ok(Value::Map(entries))
+            }
+            object => Err(Diagnostic::error(
+                ErrorCode::InvalidOperand,
+                format!("Cannot change an element of {}.", object),
+                *span,
+            )),
+        }
+    }
+
+    /// Converts a zero-based index into a position within `len` elements.
+    fn position(&self, index: Value, len: usize, span: Span) -> Result<usize, Diagnostic> {
+        let whole = match index {
+            Value::Integer(i) => Some(i),
+            Value::Number(n) if n.fract() == 0.0 => Some(n as i64),
+            _ => None,
+        };
+        match whole {
+            Some(n) => {
+                if n >= 0 && (n as usize) < len {
+                    Ok(n as usize)
+                } else {
+                    Err(Diagnostic::error(
+                        ErrorCode::IndexOutOfBounds,
+                        format!("Index {} is out of bounds for length {}.", n, len),
+                        span,
+                    ))
+                }
+            }
+            None => Err(Diagnostic::error(
+                ErrorCode::InvalidOperand,
+                format!("Index must be a whole number, found {}.", index),
+                span,
+            )),
+        }
+    }
+
+    fn slice_bounds(&self, start: i64, end: i64, len: usize, span: Span) -> Result<(usize, usize), Diagnostic> {
+        if start < 0 || start > end || end as usize > len {
+            return Err(Diagnostic::error(
+                ErrorCode::IndexOutOfBounds,
+                format!("Range {}..{} is out of bounds for length {}.", start, end, len),
+                span,
+            ));
+        }
+        Ok((start as usize, end as usize))
+    }
+
+    fn range_bound(&self, value: Value, span: Span) -> Result<i64, Diagnostic> {
+        match value {
+            Value::Integer(i) => Ok(i),
+            Value::Number(n) if n.fract() == 0.0 => Ok(n as i64),
+            _ => Err(Diagnostic::error(
+                ErrorCode::InvalidOperand,
+                "Range bounds must be whole numbers.",
+                span,
+            )),
+        }
+    }
+
+    fn child_scope(&self) -> Rc<RefCell<Environment>> {
+        Rc::new(RefCell::new(Environment::with_enclosing(Rc::clone(&self.environment))))
+    }
+
+    fn evaluate(&mut self, expr: Expr) -> Result<Value, Signal> {
+        let span = expr.span;
+        match expr.kind {
+            ExprKind::Binary { left, operator: Token::Equal, right } if self.is_constructor_pattern(&right) => {
+                let subject = self.evaluate(*left)?;
+                Ok(Value::Boolean(self.match_pattern(&subject, *right)?.is_some()))
+            }
+            ExprKind::Binary { left, operator, right } => {
                 let left_value = self.evaluate(*left)?;
                 let right_value = self.evaluate(*right)?;
                 match operator {
-                    Token::Plus => self.binary_plus(left_value, right_value),
-                    Token::Minus => self.binary_minus(left_value, right_value),
-                    Token::Star => self.binary_multiply(left_value, right_value),
-                    Token::Slash => self.binary_divide(left_value, right_value),
-                    Token::Is => Ok(Value::Boolean(self.is_equal(left_value, right_value))),
+                    Token::Plus => self.binary_plus(left_value, right_value, span),
+                    Token::Minus => self.binary_minus(left_value, right_value, span),
+                    Token::Star => self.binary_multiply(left_value, right_value, span),
+                    Token::Slash => self.binary_divide(left_value, right_value, span),
+                    Token::IntegerDivide => self.binary_integer_divide(left_value, right_value, span),
+                    Token::Modulo => self.binary_modulo(left_value, right_value, span),
+                    Token::Power => self.binary_power(left_value, right_value, span),
+                    // `&` joins the display forms of any two values
+                    Token::Ampersand => Ok(Value::String(format!("{}{}", left_value, right_value))),
+                    Token::Is | Token::Equal => Ok(Value::Boolean(self.is_equal(left_value, right_value))),
                     Token::EqualEqual => Ok(Value::Boolean(self.is_equal(left_value, right_value))),
                     Token::NotEqual => Ok(Value::Boolean(!self.is_equal(left_value, right_value))),
-                    Token::Greater => self.compare_greater(left_value, right_value),
-                    Token::Less => self.compare_less(left_value, right_value),
-                    Token::GreaterEqual => self.compare_greater_equal(left_value, right_value),
-                    Token::LessEqual => self.compare_less_equal(left_value, right_value),
-                    _ => Err("Invalid binary operator.".to_string()),
+                    Token::Greater => self.compare_greater(left_value, right_value, span),
+                    Token::Less => self.compare_less(left_value, right_value, span),
+                    Token::GreaterEqual => self.compare_greater_equal(left_value, right_value, span),
+                    Token::LessEqual => self.compare_less_equal(left_value, right_value, span),
+                    _ => Err(Diagnostic::error(ErrorCode::InvalidOperator, "Invalid binary operator.", span)),
+                }
+                .map_err(Signal::from)
+            }
+            ExprKind::Logical { left, operator, right } => {
+                let left_value = self.evaluate(*left)?;
+                let left_truthy = self.is_truthy(left_value);
+                // Skip the right side when the left already decides the answer
+                match operator {
+                    Token::And if !left_truthy => Ok(Value::Boolean(false)),
+                    Token::Or if left_truthy => Ok(Value::Boolean(true)),
+                    _ => {
+                        let right_value = self.evaluate(*right)?;
+                        Ok(Value::Boolean(self.is_truthy(right_value)))
+                    }
                 }
             }
-            Expr::Grouping(expr) => self.evaluate(*expr),
-            Expr::Literal(literal) => Ok(self.literal_to_value(literal)),
-            Expr::Unary { operator, right } => {
+            ExprKind::Grouping(expr) => self.evaluate(*expr),
+            ExprKind::Interpolation(pieces) => {
+                let mut text = String::new();
+                for piece in pieces {
+                    text.push_str(&self.evaluate(piece)?.to_string());
+                }
+                Ok(Value::String(text))
+            }
+            ExprKind::Literal(literal) => Ok(self.literal_to_value(literal)),
+            ExprKind::Unary { operator, right } => {
                 let right_value = self.evaluate(*right)?;
                 match operator {
-                    Token::Minus => self.unary_minus(right_value),
-                    Token::ExclamationMark => Ok(Value::Boolean(!self.is_truthy(right_value))),
-                    _ => Err("Invalid unary operator.".to_string()),
+                    Token::Minus => self.unary_minus(right_value, span),
+                    Token::Not => Ok(Value::Boolean(!self.is_truthy(right_value))),
+                    _ => Err(Diagnostic::error(ErrorCode::InvalidOperator, "Invalid unary operator.", span)),
                 }
+                .map_err(Signal::from)
             }
-            Expr::Variable(name) => {
-                self.environment
-                    .get(&name)
-                    .ok_or_else(|| format!("Undefined variable '{}'.", name))
+            ExprKind::Variable(name) => {
+                let value = self.environment.borrow().get(&name);
+                value.ok_or_else(|| {
+                    let error = Diagnostic::error(
+                        ErrorCode::UndefinedVariable,
+                        format!("Undefined variable '{}'.", name),
+                        span,
+                    );
+                    self.suggest_name(error, &name, span).into()
+                })
             }
-            Expr::FunctionCall { name, arguments, named_arguments: _ } => {
+            ExprKind::FunctionCall { callee, arguments, named_arguments } => {
                 // Handle built-in functions
-                if name == "print" || name == "write" {
-                    if let Some(arg) = arguments.get(0) {
+                if matches!(&callee.kind, ExprKind::Variable(name) if name == "print" || name == "write") {
+                    if let Some(arg) = arguments.first() {
                         let arg = arg.clone();
                         let value = self.evaluate(arg)?;
-                        let display_value = match &value {
-                            Value::String(s) => s.clone(),
-                            _ => format!("{:?}", value)
-                        };
-                        println!("{}", display_value);
+                        println!("{}", value);
                         // Return Nil for write/print (Bliss type)
                         Ok(Value::Nil)
                     } else {
                         Ok(Value::Nil)
                     }
                 } else {
-                    // Look up the function in the environment
-                    match self.environment.get(&name) {
-                        Some(Value::Function { name: _, params, body }) => {
-                            // Create a new environment for the function call
-                            let mut function_env = Environment::new();
-                            
-                            // Evaluate arguments and bind them to parameters
-                            for (i, (param_name, _)) in params.iter().enumerate() {
-                                if let Some(arg) = arguments.get(i) {
-                                    let arg_value = self.evaluate(arg.clone())?;
-                                    function_env.define(param_name.clone(), arg_value);
-                                } else {
-                                    return Err(format!("Missing argument for parameter '{}'", param_name));
-                                }
-                            }
-                            
-                            // Save the current environment
-                            let previous_env = std::mem::replace(&mut self.environment, function_env);
-                            
-                            // Execute the function body
-                            let mut result = Value::Nil;
-                            for stmt in body.clone() {
-                                result = self.execute(stmt)?;
+                    let callee_span = callee.span;
+                    let function = match callee.kind {
+                        // Look up the function in the environment
+                        ExprKind::Variable(name) => {
+                            let function = self.environment.borrow().get(&name);
+                            function.ok_or_else(|| {
+                                let error = Diagnostic::error(
+                                    ErrorCode::UndefinedFunction,
+                                    format!("Function '{}' not implemented", name),
+                                    span,
+                                );
+                                self.suggest_name(error, &name, callee_span)
+                            })?
+                        }
+                        // Anything else, such as a lambda in parentheses, evaluates to the function
+                        kind => self.evaluate(Expr::new(kind, callee.span))?,
+                    };
+                    let mut values = Vec::with_capacity(arguments.len());
+                    for arg in arguments {
+                        let arg_span = arg.span;
+                        values.push((self.evaluate(arg)?, arg_span));
+                    }
+                    let mut named_values = Vec::with_capacity(named_arguments.len());
+                    for (name, arg) in named_arguments {
+                        let arg_span = arg.span;
+                        named_values.push((name, self.evaluate(arg)?, arg_span));
+                    }
+                    self.call_value(function, values, named_values, span)
+                }
+            }
+            ExprKind::TypeFunctionCall { object, function } => {
+                match self.evaluate(*object)? {
+                    Value::Record { type_name, fields } | Value::Variant { tag: type_name, fields, .. } => fields
+                        .into_iter()
+                        .find(|(field, _)| *field == function)
+                        .map(|(_, value)| value)
+                        .ok_or_else(|| Diagnostic::error(
+                            ErrorCode::UnknownField,
+                            format!("'{}' has no field '{}'.", type_name, function),
+                            span,
+                        ).into()),
+                    Value::Integer(i) if function == "to-number" => Ok(Value::Number(i as f64)),
+                    Value::Number(n) if function == "to-integer" => Ok(self.to_integer(n, span)?),
+                    Value::Module { name, environment } => {
+                        let member = environment.borrow().get_local(&function);
+                        member.ok_or_else(|| Diagnostic::error(
+                            ErrorCode::UndefinedVariable,
+                            format!("Module '{}' has no member '{}'.", name, function),
+                            span,
+                        ).into())
+                    }
+                    other => Err(Diagnostic::error(
+                        ErrorCode::InvalidOperand,
+                        format!("Cannot access '{}' on {}.", function, other),
+                        span,
+                    ).into()),
+                }
+            }
+            ExprKind::List(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::List(values))
+            }
+            ExprKind::Tuple(elements) => {
+                let mut values = Vec::with_capacity(elements.len());
+                for element in elements {
+                    values.push(self.evaluate(element)?);
+                }
+                Ok(Value::Tuple(values))
+            }
+            ExprKind::Map(entries) => {
+                let mut values: Vec<(Value, Value)> = Vec::with_capacity(entries.len());
+                for (key, value) in entries {
+                    let key = self.evaluate(key)?;
+                    let value = self.evaluate(value)?;
+                    // A repeated key keeps its first position but takes the later value
+                    match values.iter_mut().find(|(existing, _)| self.is_equal(existing.clone(), key.clone())) {
+                        Some(entry) => entry.1 = value,
+                        None => values.push((key, value)),
+                    }
+                }
+                Ok(Value::Map(values))
+            }
+            ExprKind::AccessExpression { object, index } => {
+                let object = self.evaluate(*object)?;
+                let index_span = index.span;
+                let index = self.evaluate(*index)?;
+                Ok(self.index(object, index, index_span)?)
+            }
+            ExprKind::Of { value, cases, default } => self.evaluate_of(*value, cases, default),
+            ExprKind::Try { operand, handler } => {
+                let operand_span = operand.span;
+                match self.evaluate(*operand)? {
+                    Value::Variant { type_name, tag, mut fields } if type_name == "Result" => {
+                        let payload = fields.pop().map(|(_, value)| value).unwrap_or(Value::Nil);
+                        if tag == "Success" {
+                            return Ok(payload);
+                        }
+                        let error = match handler {
+                            Some(handler) => {
+                                let handler = self.evaluate(*handler)?;
+                                self.call_value(handler, vec![(payload, operand_span)], Vec::new(), span)?
                             }
-                            
-                            // Restore the previous environment
-                            self.environment = previous_env;
-                            
-                            Ok(result)
-                        },
-                        _ => Err(format!("Function '{}' not implemented", name))
+                            None => Value::Variant {
+                                type_name,
+                                tag,
+                                fields: vec![("message".to_string(), payload)],
+                            },
+                        };
+                        Err(Signal::Propagate(error, span))
+                    }
+                    other => Err(Diagnostic::error(
+                        ErrorCode::TypeMismatch,
+                        format!("'try' expects a Result, found {}.", other),
+                        operand_span,
+                    ).into()),
+                }
+            }
+            ExprKind::Lambda { params, body } => Ok(Value::Function {
+                name: "lambda".to_string(),
+                params,
+                return_type: Type::Primitive("Any".to_string()),
+                body,
+                closure: Rc::clone(&self.environment),
+            }),
+            ExprKind::Record { type_name, fields } => {
+                let declared = match self.environment.borrow().get(&type_name) {
+                    Some(Value::RecordType { fields, .. }) => fields,
+                    _ => {
+                        return Err(Diagnostic::error(
+                            ErrorCode::UndefinedType,
+                            format!("Undefined record type '{}'.", type_name),
+                            span,
+                        ).into())
+                    }
+                };
+                let provided = self.evaluate_fields(fields)?;
+                if let Some((name, _, field_span)) = provided
+                    .iter()
+                    .find(|(name, _, _)| !declared.iter().any(|(field, _)| field == name))
+                {
+                    return Err(Diagnostic::error(
+                        ErrorCode::UnknownField,
+                        format!("Record '{}' has no field '{}'.", type_name, name),
+                        *field_span,
+                    ).into());
+                }
+                let mut values: Vec<(String, Value)> = Vec::with_capacity(declared.len());
+                for (name, field_type) in &declared {
+                    match provided.iter().find(|(field, _, _)| field == name) {
+                        Some((_, value, field_span)) => {
+                            self.check_field_type(&type_name, name, field_type, value, *field_span)?;
+                            values.push((name.clone(), conform(value.clone(), field_type)));
+                        }
+                        None => {
+                            return Err(Diagnostic::error(
+                                ErrorCode::MissingField,
+                                format!("Missing field '{}' for record '{}'.", name, type_name),
+                                span,
+                            ).into())
+                        }
+                    }
+                }
+                Ok(Value::Record { type_name, fields: values })
+            }
+            ExprKind::RecordUpdate { record, fields } => {
+                let (type_name, mut values) = match self.evaluate(*record)? {
+                    Value::Record { type_name, fields } => (type_name, fields),
+                    other => {
+                        return Err(Diagnostic::error(
+                            ErrorCode::InvalidOperand,
+                            format!("Cannot use 'with' on {}.", other),
+                            span,
+                        ).into())
+                    }
+                };
+                let declared = match self.environment.borrow().get(&type_name) {
+                    Some(Value::RecordType { fields, .. }) => fields,
+                    _ => Vec::new(),
+                };
+                for (name, mut value, field_span) in self.evaluate_fields(fields)? {
+                    if let Some((_, field_type)) = declared.iter().find(|(field, _)| *field == name) {
+                        self.check_field_type(&type_name, &name, field_type, &value, field_span)?;
+                        value = conform(value, field_type);
+                    }
+                    match values.iter_mut().find(|(field, _)| *field == name) {
+                        Some(slot) => slot.1 = value,
+                        None => {
+                            return Err(Diagnostic::error(
+                                ErrorCode::UnknownField,
+                                format!("Record '{}' has no field '{}'.", type_name, name),
+                                field_span,
+                            ).into())
+                        }
                     }
                 }
+                Ok(Value::Record { type_name, fields: values })
+            }
+            ExprKind::Range { start, end } => {
+                let start = self.evaluate(*start)?;
+                let end = self.evaluate(*end)?;
+                Ok(Value::Range {
+                    start: self.range_bound(start, span)?,
+                    end: self.range_bound(end, span)?,
+                })
             }
-            // Add placeholder implementations for other expression types
-            _ => Ok(Value::Nil),
         }
     }
 
-    fn binary_plus(&self, left: Value, right: Value) -> Result<Value, String> {
+    /// Offers the closest name in scope as a fix for a misspelled `name`.
+    fn suggest_name(&self, error: Diagnostic, name: &str, span: Span) -> Diagnostic {
+        let names = self.environment.borrow().names();
+        match similar_name(name, names.iter().map(String::as_str)) {
+            Some(similar) => error.with_suggestion(span, similar, "a similar name is in scope"),
+            None => error,
+        }
+    }
+
+    fn binary_plus(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
         match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
             (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
-            _ => Err("Operands must be two numbers or two strings.".to_string()),
+            (left, right) => match numeric_operands(left, right) {
+                Some(Operands::Integers(l, r)) => checked(l.checked_add(r), span),
+                Some(Operands::Numbers(l, r)) => Ok(Value::Number(l + r)),
+                None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be two numbers or two strings.", span)),
+            },
         }
     }
 
-    fn binary_minus(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
-            _ => Err("Operands must be numbers.".to_string()),
+    fn binary_minus(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => checked(l.checked_sub(r), span),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Number(l - r)),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
         }
     }
 
-    fn binary_multiply(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
-            _ => Err("Operands must be numbers.".to_string()),
+    fn binary_multiply(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => checked(l.checked_mul(r), span),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Number(l * r)),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
         }
     }
 
-    fn binary_divide(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => {
-                if r == 0.0 {
-                    Err("Division by zero.".to_string())
-                } else {
-                    Ok(Value::Number(l / r))
-                }
+    /// `/` always divides exactly, so two integers give a `#Number`.
+    fn binary_divide(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        let (l, r) = match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => (l as f64, r as f64),
+            Some(Operands::Numbers(l, r)) => (l, r),
+            None => return Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
+        };
+        if r == 0.0 {
+            Err(Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero.", span))
+        } else {
+            Ok(Value::Number(l / r))
+        }
+    }
+
+    /// `//` rounds toward negative infinity, and `%` below takes the sign
+    /// of the divisor, so `a = (a // b) * b + a % b`.
+    fn binary_integer_divide(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(_, 0)) => Err(Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero.", span)),
+            Some(Operands::Integers(l, r)) => {
+                let quotient = l.checked_div(r);
+                checked(quotient.map(|q| if l % r != 0 && (l < 0) != (r < 0) { q - 1 } else { q }), span)
             }
-            _ => Err("Operands must be numbers.".to_string()),
+            Some(Operands::Numbers(_, 0.0)) => Err(Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero.", span)),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Number((l / r).floor())),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
         }
     }
 
-    fn compare_greater(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l > r)),
-            _ => Err("Operands must be numbers.".to_string()),
+    fn binary_modulo(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(_, 0)) => Err(Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero.", span)),
+            Some(Operands::Integers(l, r)) => {
+                checked(l.checked_rem(r).map(|m| if m != 0 && (m < 0) != (r < 0) { m + r } else { m }), span)
+            }
+            Some(Operands::Numbers(_, 0.0)) => Err(Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero.", span)),
+            Some(Operands::Numbers(l, r)) => {
+                let m = l % r;
+                Ok(Value::Number(if m != 0.0 && (m < 0.0) != (r < 0.0) { m + r } else { m }))
+            }
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
         }
     }
 
-    fn compare_less(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l < r)),
-            _ => Err("Operands must be numbers.".to_string()),
+    /// An integer raised to a negative power gives a `#Number`.
+    fn binary_power(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) if r < 0 => Ok(Value::Number((l as f64).powf(r as f64))),
+            Some(Operands::Integers(l, r)) => checked(u32::try_from(r).ok().and_then(|r| l.checked_pow(r)), span),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Number(l.powf(r))),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
         }
     }
 
-    fn compare_greater_equal(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l >= r)),
-            _ => Err("Operands must be numbers.".to_string()),
+    /// `'to-integer`: drops the fractional part of `n`.
+    fn to_integer(&self, n: f64, span: Span) -> Result<Value, Diagnostic> {
+        let truncated = n.trunc();
+        // i64::MAX is not exactly representable, so compare against 2^63
+        if truncated >= -(2f64.powi(63)) && truncated < 2f64.powi(63) {
+            Ok(Value::Integer(truncated as i64))
+        } else {
+            Err(Diagnostic::error(
+                ErrorCode::IntegerOverflow,
+                format!("{} does not fit in an #Integer.", n),
+                span,
+            ))
         }
     }
 
-    fn compare_less_equal(&self, left: Value, right: Value) -> Result<Value, String> {
-        match (left, right) {
-            (Value::Number(l), Value::Number(r)) => Ok(Value::Boolean(l <= r)),
-            _ => Err("Operands must be numbers.".to_string()),
+    fn compare_greater(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => Ok(Value::Boolean(l > r)),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Boolean(l > r)),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
+        }
+    }
+
+    fn compare_less(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => Ok(Value::Boolean(l < r)),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Boolean(l < r)),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
+        }
+    }
+
+    fn compare_greater_equal(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => Ok(Value::Boolean(l >= r)),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Boolean(l >= r)),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
         }
     }
 
-    fn unary_minus(&self, value: Value) -> Result<Value, String> {
+    fn compare_less_equal(&self, left: Value, right: Value, span: Span) -> Result<Value, Diagnostic> {
+        match numeric_operands(left, right) {
+            Some(Operands::Integers(l, r)) => Ok(Value::Boolean(l <= r)),
+            Some(Operands::Numbers(l, r)) => Ok(Value::Boolean(l <= r)),
+            None => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operands must be numbers.", span)),
+        }
+    }
+
+    fn unary_minus(&self, value: Value, span: Span) -> Result<Value, Diagnostic> {
         match value {
             Value::Number(n) => Ok(Value::Number(-n)),
-            _ => Err("Operand must be a number.".to_string()),
+            Value::Integer(i) => checked(i.checked_neg(), span),
+            _ => Err(Diagnostic::error(ErrorCode::InvalidOperand, "Operand must be a number.", span)),
         }
     }
 
     fn literal_to_value(&self, literal: Literal) -> Value {
         match literal {
             Literal::Number(n) => Value::Number(n),
-            Literal::Integer(i) => Value::Number(i as f64),
+            Literal::Integer(i) => Value::Integer(i),
             Literal::String(s) => Value::String(s),
             Literal::Decision(b) => Value::Boolean(b),
             Literal::Nothing => Value::Nil,
@@ -334,12 +1529,55 @@ impl Interpreter {
     }
 
     fn is_equal(&self, left: Value, right: Value) -> bool {
+        if let (Value::Number(_) | Value::Integer(_), Value::Number(_) | Value::Integer(_)) = (&left, &right) {
+            return match numeric_operands(left, right) {
+                Some(Operands::Integers(l, r)) => l == r,
+                Some(Operands::Numbers(l, r)) => l == r,
+                None => false,
+            };
+        }
         match (left, right) {
-            (Value::Number(l), Value::Number(r)) => l == r,
             (Value::String(l), Value::String(r)) => l == r,
             (Value::Boolean(l), Value::Boolean(r)) => l == r,
             (Value::Nil, Value::Nil) => true,
+            (Value::List(l), Value::List(r)) | (Value::Tuple(l), Value::Tuple(r)) => {
+                l.len() == r.len() && l.into_iter().zip(r).all(|(l, r)| self.is_equal(l, r))
+            }
+            // Maps are equal when they hold the same entries, in any order
+            (Value::Map(l), Value::Map(r)) => {
+                l.len() == r.len()
+                    && l.into_iter().all(|(key, value)| {
+                        r.iter().any(|(other_key, other_value)| {
+                            self.is_equal(key.clone(), other_key.clone())
+                                && self.is_equal(value.clone(), other_value.clone())
+                        })
+                    })
+            }
+            (Value::Range { start: ls, end: le }, Value::Range { start: rs, end: re }) => ls == rs && le == re,
+            (
+                Value::Record { type_name: left_type, fields: left_fields },
+                Value::Record { type_name: right_type, fields: right_fields },
+            ) => {
+                left_type == right_type
+                    && left_fields.len() == right_fields.len()
+                    && left_fields
+                        .into_iter()
+                        .zip(right_fields)
+                        .all(|((_, l), (_, r))| self.is_equal(l, r))
+            }
+            (
+                Value::Variant { type_name: left_type, tag: left_tag, fields: left_fields },
+                Value::Variant { type_name: right_type, tag: right_tag, fields: right_fields },
+            ) => {
+                left_type == right_type
+                    && left_tag == right_tag
+                    && left_fields.len() == right_fields.len()
+                    && left_fields
+                        .into_iter()
+                        .zip(right_fields)
+                        .all(|((_, l), (_, r))| self.is_equal(l, r))
+            }
             _ => false,
         }
     }
-} 
\ No newline at end of file
+}
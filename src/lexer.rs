@@ -1,7 +1,18 @@
 use logos::Logos;
 use std::fmt;
 
+/// Why the lexer rejected a piece of input.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum LexError {
+    #[default]
+    Invalid,
+    /// A string whose `{` (at this byte offset) never gets its `}`.
+    UnclosedInterpolation(usize),
+}
+
 #[derive(Logos, Debug, PartialEq, Clone)]
+#[logos(error = LexError)]
+#[allow(clippy::upper_case_acronyms)]
 pub enum Token {
     // Keywords
     #[token("by")]
@@ -34,6 +45,12 @@ pub enum Token {
     Continue,
     #[token("produce")]
     Produce, // Early return
+    #[token("try")]
+    Try,
+    #[token("rescue")]
+    Rescue,
+    #[token("catch")]
+    Catch,
     #[token("import")]
     Import,
     #[token("module")]
@@ -50,6 +67,12 @@ pub enum Token {
     Variant,
     #[token("write")]
     Write, // Print
+    #[token("and")]
+    And,
+    #[token("or")]
+    Or,
+    #[token("not")]
+    Not,
     #[token("otherwise")]
     Otherwise, // Default case in switch
 
@@ -76,10 +99,6 @@ pub enum Token {
     MapType,
     #[token("#Tuple")]
     TupleType,
-    #[token("#Result")]
-    ResultType,
-    #[token("#Shape")]
-    ShapeType,
 
     // Boolean values
     #[token("yes")]
@@ -89,11 +108,6 @@ pub enum Token {
     #[token("nothing")]
     Nothing,
 
-    // Comment tokens
-    #[token("!", priority = 2)]
-    ExclamationMark, // Single line comment
-    #[token("!doc")]
-    DocComment,
 
     // Symbols
     #[token("+")]
@@ -130,6 +144,8 @@ pub enum Token {
     Range,
     #[token("&")]
     Ampersand, // String concatenation
+    #[token("@")]
+    At, // Parameter declarations
     #[token(":")]
     Colon,
     #[token("->")]
@@ -146,6 +162,8 @@ pub enum Token {
     GreaterEqual,
     #[token("<=")]
     LessEqual,
+    #[token("=")]
+    Equal,
     #[token("==")]
     EqualEqual,
     #[token("!=")]
@@ -154,58 +172,364 @@ pub enum Token {
     FatArrow, // For lambdas
 
     // Literals
-    #[regex(r"-?[0-9]+(\.[0-9]+)?", |lex| lex.slice().parse::<f64>().map_err(|_| ()))]
+    #[regex(r"[0-9]+\.[0-9]+", |lex| lex.slice().parse::<f64>().map_err(|_| LexError::Invalid))]
     Number(f64),
-    #[regex(r#""[^"]*""#, |lex| lex.slice()[1..lex.slice().len()-1].to_string())]
-    String(String),
-    #[regex(r#""""[\s\S]*""""#, |lex| {
-        let content = lex.slice();
-        let trimmed = &content[3..content.len()-3];
-        Ok::<String, ()>(trimmed.to_string())
-    })]
-    MultilineString(String),
+    #[regex(r"[0-9]+", |lex| lex.slice().parse::<i64>().map_err(|_| LexError::Invalid))]
+    Integer(i64),
+    #[token("\"", |lex| lex_string(lex, "\""))]
+    #[token("\"\"\"", |lex| lex_string(lex, "\"\"\""))]
+    String(Vec<StringPart>),
     #[regex(r"[a-zA-Z][a-zA-Z0-9_\-]*", |lex| lex.slice().to_string())]
     Identifier(String), // Support for kebab-case identifiers
 
     // Whitespace and comments
     #[regex(r"[ \t\n\f]+", logos::skip)]
     Whitespace,
-    #[regex(r"!([^\n]*)", logos::skip, priority = 3)]
+    // Comments run from `!` to the end of the line; `!=` is still an operator.
+    // `!doc """ ... """` blocks may span several lines.
+    #[token("!", skip_comment)]
     Comment,
 
+    // Input the lexer could not make sense of, kept verbatim for diagnostics
+    Error(String),
+    // The `{` of a string interpolation that is never closed
+    UnclosedInterpolation,
+
     // End of file
     #[end]
     EOF,
 }
 
+fn skip_comment(lex: &mut logos::Lexer<Token>) -> logos::Skip {
+    let rest = lex.remainder();
+    let doc_body = rest
+        .strip_prefix("doc")
+        .map(|after| after.trim_start_matches([' ', '\t']))
+        .and_then(|after| after.strip_prefix("\"\"\""));
+    let length = match doc_body {
+        // An unterminated doc block runs to the end of the input
+        Some(body) => rest.len() - body.len() + body.find("\"\"\"").map_or(body.len(), |end| end + 3),
+        None => rest.find('\n').unwrap_or(rest.len()),
+    };
+    lex.bump(length);
+    logos::Skip
+}
+
+/// A piece of a string literal: text with its escapes resolved, or the
+/// tokens of an embedded `{expression}`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StringPart {
+    Text(String),
+    Code(Vec<SpannedToken>),
+}
+
+/// Lexes the rest of a string opened by `delimiter`. An unterminated string
+/// swallows the rest of the input and becomes an error token; a string with
+/// an unclosed `{` runs to its closing delimiter and reports the brace.
+fn lex_string(lex: &mut logos::Lexer<Token>, delimiter: &str) -> Result<Vec<StringPart>, LexError> {
+    let source = lex.source();
+    let start = lex.span().end;
+    let length = match string_length(&source[start..], delimiter) {
+        Ok(length) => length,
+        Err(Unclosed::String) => {
+            lex.bump(source.len() - start);
+            return Err(LexError::Invalid);
+        }
+        Err(Unclosed::Interpolation(brace)) => {
+            let rest = &source[start + brace..];
+            let end = literal_length(rest, delimiter).map_or(rest.len(), |length| length + delimiter.len());
+            lex.bump(brace + end);
+            return Err(LexError::UnclosedInterpolation(start + brace));
+        }
+    };
+    lex.bump(length + delimiter.len());
+
+    let body = &source[start..start + length];
+    let (first, end, indent) = if delimiter == "\"\"\"" {
+        text_block_layout(body)
+    } else {
+        (0, body.len(), 0)
+    };
+
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    // Leading whitespace still to drop from the current line
+    let mut strip = if first > 0 { indent } else { 0 };
+    let mut i = first;
+    while i < end {
+        let c = body[i..].chars().next().unwrap_or_default();
+        if strip > 0 && (c == ' ' || c == '\t') {
+            strip -= 1;
+            i += 1;
+            continue;
+        }
+        strip = 0;
+        match c {
+            '\n' => {
+                text.push('\n');
+                strip = indent;
+                i += 1;
+            }
+            '\\' => {
+                let escaped = body[i + 1..].chars().next();
+                match escaped {
+                    Some('n') => text.push('\n'),
+                    Some('t') => text.push('\t'),
+                    Some('r') => text.push('\r'),
+                    Some(c @ ('\\' | '"' | '{' | '}')) => text.push(c),
+                    // Unknown escapes are kept as written
+                    Some(other) => {
+                        text.push('\\');
+                        text.push(other);
+                    }
+                    None => text.push('\\'),
+                }
+                i += 1 + escaped.map_or(0, char::len_utf8);
+            }
+            '{' => {
+                let code_length = code_length(&body[i + 1..]).unwrap_or(0);
+                if !text.is_empty() {
+                    parts.push(StringPart::Text(std::mem::take(&mut text)));
+                }
+                let code_start = start + i + 1;
+                let tokens = Lexer::within(source, code_start..code_start + code_length).collect();
+                parts.push(StringPart::Code(tokens));
+                i += code_length + 2;
+            }
+            _ => {
+                text.push(c);
+                i += c.len_utf8();
+            }
+        }
+    }
+    if !text.is_empty() || parts.is_empty() {
+        parts.push(StringPart::Text(text));
+    }
+    Ok(parts)
+}
+
+/// What a string literal was missing when the input ran out.
+enum Unclosed {
+    String,
+    /// An embedded expression opened at this byte offset.
+    Interpolation(usize),
+}
+
+/// Byte length of a string body up to (not including) its closing `delimiter`.
+fn string_length(rest: &str, delimiter: &str) -> Result<usize, Unclosed> {
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with(delimiter) {
+            return Ok(i);
+        }
+        match rest.as_bytes()[i] {
+            b'\\' => i += 1 + rest[i + 1..].chars().next().map_or(0, char::len_utf8),
+            b'{' => match code_length(&rest[i + 1..]) {
+                Some(length) => i += length + 2,
+                None => return Err(Unclosed::Interpolation(i)),
+            },
+            _ => i += rest[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    Err(Unclosed::String)
+}
+
+/// Byte length of a string body up to its closing `delimiter`, reading
+/// braces as plain text. Used to skip past a string with a broken `{`.
+fn literal_length(rest: &str, delimiter: &str) -> Option<usize> {
+    let mut i = 0;
+    while i < rest.len() {
+        if rest[i..].starts_with(delimiter) {
+            return Some(i);
+        }
+        match rest.as_bytes()[i] {
+            b'\\' => i += 1 + rest[i + 1..].chars().next().map_or(0, char::len_utf8),
+            _ => i += rest[i..].chars().next().map_or(1, char::len_utf8),
+        }
+    }
+    None
+}
+
+/// Byte length of an embedded expression up to its closing `}`, stepping over
+/// nested braces and strings.
+fn code_length(rest: &str) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = 0;
+    while i < rest.len() {
+        match rest.as_bytes()[i] {
+            b'"' => {
+                let delimiter = if rest[i..].starts_with("\"\"\"") { "\"\"\"" } else { "\"" };
+                let after = i + delimiter.len();
+                i = after + string_length(&rest[after..], delimiter).ok()? + delimiter.len();
+                continue;
+            }
+            b'{' => depth += 1,
+            b'}' if depth == 0 => return Some(i),
+            b'}' => depth -= 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Where the text of a `"""` block starts and ends, and how much indentation
+/// to drop from each line. A line break right after the opening quotes and a
+/// blank line before the closing quotes are not part of the text; the common
+/// indentation of the remaining non-blank lines is removed.
+fn text_block_layout(body: &str) -> (usize, usize, usize) {
+    let first = if body.starts_with('\n') {
+        1
+    } else if body.starts_with("\r\n") {
+        2
+    } else {
+        0
+    };
+    let end = match body.rfind('\n') {
+        Some(last) if last >= first && body[last + 1..].trim().is_empty() => last,
+        _ => body.len(),
+    };
+    let indent = body[first..end.max(first)]
+        .split('\n')
+        // Text on the same line as the opening quotes keeps its spacing
+        .skip(if first == 0 { 1 } else { 0 })
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches([' ', '\t']).len())
+        .min()
+        .unwrap_or(0);
+    (first, end.max(first), indent)
+}
+
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Token::Number(n) => write!(f, "{}", n),
-            Token::String(s) => write!(f, "\"{}\"", s),
-            Token::MultilineString(s) => write!(f, "\"\"\"{}\"\"\"", s),
+            Token::Integer(i) => write!(f, "{}", i),
+            Token::String(parts) => {
+                write!(f, "\"")?;
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => write!(f, "{}", text)?,
+                        StringPart::Code(_) => write!(f, "{{...}}")?,
+                    }
+                }
+                write!(f, "\"")
+            }
             Token::Identifier(name) => write!(f, "{}", name),
+            Token::Error(text) => write!(f, "{}", text),
             _ => write!(f, "{:?}", self),
         }
     }
 }
 
+/// A region of source text: byte offsets into the input plus the 1-based
+/// line and column of its first character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize, line: usize, column: usize) -> Self {
+        Self { start, end, line, column }
+    }
+
+    /// Returns a span running from the start of `self` to the end of `other`.
+    pub fn to(self, other: Span) -> Span {
+        Span {
+            end: other.end.max(self.end),
+            ..self
+        }
+    }
+}
+
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 pub struct Lexer<'a> {
     inner: logos::Lexer<'a, Token>,
+    // Line bookkeeping: newlines are counted lazily up to `scanned`.
+    line: usize,
+    line_start: usize,
+    scanned: usize,
+    finished: bool,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
             inner: Token::lexer(input),
+            line: 1,
+            line_start: 0,
+            scanned: 0,
+            finished: false,
+        }
+    }
+
+    /// Lexes `source[range]` while reporting spans against all of `source`.
+    fn within(source: &'a str, range: std::ops::Range<usize>) -> Self {
+        let mut inner = Token::lexer(&source[..range.end]);
+        inner.bump(range.start);
+        Self {
+            inner,
+            line: 1,
+            line_start: 0,
+            scanned: 0,
+            finished: false,
         }
     }
+
+    fn span(&mut self, range: std::ops::Range<usize>) -> Span {
+        let source = self.inner.source();
+        for (i, byte) in source[self.scanned..range.start].bytes().enumerate() {
+            if byte == b'\n' {
+                self.line += 1;
+                self.line_start = self.scanned + i + 1;
+            }
+        }
+        self.scanned = range.start;
+        let column = source[self.line_start..range.start].chars().count() + 1;
+        Span::new(range.start, range.end, self.line, column)
+    }
 }
 
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Token;
+    type Item = SpannedToken;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|result| result.unwrap_or(Token::EOF))
+        match self.inner.next() {
+            Some(result) => {
+                let token = match result {
+                    Ok(token) => token,
+                    Err(LexError::UnclosedInterpolation(brace)) => {
+                        let span = self.span(brace..brace + 1);
+                        return Some(SpannedToken { token: Token::UnclosedInterpolation, span });
+                    }
+                    Err(LexError::Invalid) => Token::Error(self.inner.slice().to_string()),
+                };
+                let span = self.span(self.inner.span());
+                Some(SpannedToken { token, span })
+            }
+            None if !self.finished => {
+                // Emit a trailing EOF so the parser can point at the end of input.
+                self.finished = true;
+                let end = self.inner.source().len();
+                let span = self.span(end..end);
+                Some(SpannedToken { token: Token::EOF, span })
+            }
+            None => None,
+        }
     }
-} 
\ No newline at end of file
+}
@@ -1,15 +1,24 @@
+// Diagnostics carry labels, notes and suggestions; they only travel on the
+// error path, so their size is not worth boxing away.
+#![allow(clippy::result_large_err)]
+
 mod utils;
-mod lexer;
-mod parser;
-mod evaluator;
+pub mod diagnostic;
+pub mod lexer;
+pub mod parser;
+pub mod evaluator;
+pub mod typecheck;
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
 use crate::parser::Parser;
 use crate::evaluator::Interpreter;
+use crate::diagnostic::Diagnostic;
+use crate::typecheck::TypeChecker;
 
 #[cfg_attr(feature = "wasm", wasm_bindgen)]
 pub struct Wittgenlang {
+    checker: TypeChecker,
     interpreter: Interpreter,
 }
 
@@ -20,18 +29,34 @@ impl Wittgenlang {
         utils::set_panic_hook();
         
         Self {
+            checker: TypeChecker::new(),
             interpreter: Interpreter::new(),
         }
     }
 
-    pub fn evaluate(&mut self, input: &str) -> Result<String, String> {
+    /// Parses, checks and runs `input`. Returns every syntax or type error
+    /// found, or the runtime error that stopped execution.
+    pub fn evaluate(&mut self, input: &str) -> Result<String, Vec<Diagnostic>> {
         let mut parser = Parser::new(input);
-        let statements = parser.parse()?;
-        let result = self.interpreter.interpret(statements)?;
+        let (statements, diagnostics) = parser.parse();
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        let diagnostics = self.checker.check(&statements);
+        if !diagnostics.is_empty() {
+            return Err(diagnostics);
+        }
+        let result = self.interpreter.interpret(statements).map_err(|d| vec![d])?;
         Ok(format!("{:?}", result))
     }
 }
 
+impl Default for Wittgenlang {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 // Wasm-specific implementations
 #[cfg(all(feature = "wasm", target_arch = "wasm32"))]
 #[wasm_bindgen]
@@ -43,7 +68,13 @@ impl Wittgenlang {
 
     #[wasm_bindgen]
     pub fn evaluate_wasm(&mut self, input: &str) -> Result<String, String> {
-        self.evaluate(input)
+        self.evaluate(input).map_err(|diagnostics| {
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.render(input, "playground"))
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
     }
 }
 
@@ -15,7 +15,11 @@ fn main() -> Result<(), String> {
         
         match interpreter.evaluate(&contents) {
             Ok(result) => println!("{}", result),
-            Err(e) => eprintln!("Error: {}", e),
+            Err(diagnostics) => {
+                for diagnostic in diagnostics {
+                    eprintln!("{}", diagnostic.render(&contents, filename));
+                }
+            }
         }
     } else {
         // Interactive mode
@@ -29,9 +33,14 @@ fn main() -> Result<(), String> {
             match io::stdin().read_line(&mut input) {
                 Ok(0) => break, // Ctrl+D pressed
                 Ok(_) => {
-                    match interpreter.evaluate(input.trim()) {
+                    let line = input.trim();
+                    match interpreter.evaluate(line) {
                         Ok(result) => println!("{}", result),
-                        Err(e) => eprintln!("Error: {}", e),
+                        Err(diagnostics) => {
+                            for diagnostic in diagnostics {
+                                eprintln!("{}", diagnostic.render(line, "<repl>"));
+                            }
+                        }
                     }
                 },
                 Err(e) => eprintln!("Error reading input: {}", e),
@@ -1,13 +1,36 @@
-use crate::lexer::Token;
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::lexer::{Span, SpannedToken, StringPart, Token};
+use std::collections::HashMap;
+use std::fmt;
 
 #[derive(Debug, Clone)]
-pub enum Expr {
+pub struct Expr {
+    pub kind: ExprKind,
+    pub span: Span,
+}
+
+impl Expr {
+    pub fn new(kind: ExprKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum ExprKind {
     Binary {
         left: Box<Expr>,
         operator: Token,
         right: Box<Expr>,
     },
+    /// `and` / `or`, kept apart from `Binary` because the right side may not run.
+    Logical {
+        left: Box<Expr>,
+        operator: Token,
+        right: Box<Expr>,
+    },
     Grouping(Box<Expr>),
+    /// `"text {expr} text"`: the pieces are displayed and joined in order.
+    Interpolation(Vec<Expr>),
     Literal(Literal),
     Unary {
         operator: Token,
@@ -15,7 +38,7 @@ pub enum Expr {
     },
     Variable(String),
     FunctionCall {
-        name: String,
+        callee: Box<Expr>,
         arguments: Vec<Expr>,
         named_arguments: Vec<(String, Expr)>,
     },
@@ -24,25 +47,51 @@ pub enum Expr {
         function: String,
     },
     List(Vec<Expr>),
+    /// `(a, b)`; parentheses around a single expression are a `Grouping`.
+    Tuple(Vec<Expr>),
     Map(Vec<(Expr, Expr)>),
     Record {
         type_name: String,
         fields: Vec<(String, Expr)>, 
     },
-    TypeAnnotation {
-        expr: Box<Expr>,
-        type_name: String,
-    },
+    /// `(x) -> x * 2`; an arrow body becomes a single expression statement.
     Lambda {
-        params: Vec<(String, String)>, // (name, type)
-        body: Box<Expr>,
+        params: Vec<(String, Type)>, // (name, type)
+        body: Vec<Stmt>,
     },
     AccessExpression {
         object: Box<Expr>,
         index: Box<Expr>,
     },
+    Range {
+        start: Box<Expr>,
+        end: Box<Expr>,
+    },
+    /// `record'with { field: value }`: a copy of `record` with some fields replaced.
+    RecordUpdate {
+        record: Box<Expr>,
+        fields: Vec<(String, Expr)>,
+    },
+    /// `try operand | handler`: unwraps a `Success`, or returns the `Error`
+    /// from the enclosing function after passing its payload through `handler`.
+    Try {
+        operand: Box<Expr>,
+        handler: Option<Box<Expr>>,
+    },
+    /// An `of` switch used where a value is expected.
+    Of {
+        value: Box<Expr>,
+        cases: Vec<OfCase>,
+        default: Option<Vec<Stmt>>,
+    },
 }
 
+/// One `of` arm: the values it matches and the statements it runs.
+pub type OfCase = (Vec<Expr>, Vec<Stmt>);
+
+/// The subject, arms and `otherwise` arm of an `of` switch.
+type OfParts = (Expr, Vec<OfCase>, Option<Vec<Stmt>>);
+
 #[derive(Debug, Clone)]
 pub enum Literal {
     Number(f64),
@@ -52,10 +101,15 @@ pub enum Literal {
     Nothing,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Type {
     Primitive(String),
     Custom(String),
+    /// A type applied to arguments, such as `#Optional(Text)`.
+    Generic {
+        name: String,
+        arguments: Vec<Type>,
+    },
     List(Box<Type>),
     Map(Box<Type>, Box<Type>),
     Tuple(Vec<Type>),
@@ -66,19 +120,117 @@ pub enum Type {
     },
 }
 
+impl Type {
+    /// The type a name stands for: a built-in such as `List(Number)`, or a
+    /// declared type applied to `arguments`.
+    pub fn named(name: String, mut arguments: Vec<Type>) -> Type {
+        let any = || Type::Primitive("Any".to_string());
+        match name.as_str() {
+            "Number" | "Integer" | "Text" | "Decision" | "Nothing" | "Bliss" | "Any" if arguments.is_empty() => {
+                Type::Primitive(name)
+            }
+            "List" if arguments.len() <= 1 => Type::List(Box::new(arguments.pop().unwrap_or_else(any))),
+            "Map" if arguments.is_empty() => Type::Map(Box::new(any()), Box::new(any())),
+            "Map" if arguments.len() == 2 => {
+                let value = arguments.pop().unwrap_or_else(any);
+                let key = arguments.pop().unwrap_or_else(any);
+                Type::Map(Box::new(key), Box::new(value))
+            }
+            "Tuple" => Type::Tuple(arguments),
+            _ if arguments.is_empty() => Type::Custom(name),
+            _ => Type::Generic { name, arguments },
+        }
+    }
+
+    /// Replaces type parameters by the types they are bound to, as when
+    /// `#BinaryTree(T)` is used as `#BinaryTree(Number)`.
+    pub fn substitute(&self, bindings: &HashMap<String, Type>) -> Type {
+        let all = |types: &[Type]| types.iter().map(|ty| ty.substitute(bindings)).collect();
+        match self {
+            Type::Custom(name) => bindings.get(name).cloned().unwrap_or_else(|| self.clone()),
+            Type::Primitive(_) => self.clone(),
+            Type::Generic { name, arguments } => Type::Generic {
+                name: name.clone(),
+                arguments: all(arguments),
+            },
+            Type::List(element) => Type::List(Box::new(element.substitute(bindings))),
+            Type::Map(key, value) => Type::Map(Box::new(key.substitute(bindings)), Box::new(value.substitute(bindings))),
+            Type::Tuple(elements) => Type::Tuple(all(elements)),
+            Type::Union(options) => Type::Union(all(options)),
+            Type::Function { parameters, return_type } => Type::Function {
+                parameters: all(parameters),
+                return_type: Box::new(return_type.substitute(bindings)),
+            },
+        }
+    }
+
+    /// `#T*`, shorthand for `#Optional(T)`.
+    pub fn optional(ty: Type) -> Type {
+        Type::Generic {
+            name: "Optional".to_string(),
+            arguments: vec![ty],
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Type arguments are written without their own `#`
+        let list = |types: &[Type]| {
+            types
+                .iter()
+                .map(|ty| ty.to_string().trim_start_matches('#').to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match self {
+            Type::Primitive(name) | Type::Custom(name) => write!(f, "#{}", name),
+            Type::Generic { name, arguments } => write!(f, "#{}({})", name, list(arguments)),
+            Type::List(element) => write!(f, "#List({})", list(std::slice::from_ref(element))),
+            Type::Map(key, value) => write!(f, "#Map({})", list(&[*key.clone(), *value.clone()])),
+            Type::Tuple(elements) => write!(f, "#Tuple({})", list(elements)),
+            Type::Union(options) => {
+                let options: Vec<String> = options.iter().map(Type::to_string).collect();
+                write!(f, "{}", options.join(" | "))
+            }
+            Type::Function { parameters, return_type } => {
+                write!(f, "#({}) -> {}", list(parameters), return_type)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Stmt {
+    pub kind: StmtKind,
+    pub span: Span,
+}
+
+impl Stmt {
+    pub fn new(kind: StmtKind, span: Span) -> Self {
+        Self { kind, span }
+    }
+}
+
 #[derive(Debug, Clone)]
-pub enum Stmt {
+pub enum StmtKind {
     Expression(Expr),
     Value {
         name: String,
-        type_name: String,
+        value_type: Type,
+        initializer: Expr,
+        mutable: bool,
+    },
+    /// `(a, b) is pair` binds each element of a tuple to a name.
+    Destructure {
+        names: Vec<String>,
         initializer: Expr,
         mutable: bool,
     },
     Function {
         name: String,
-        return_type: String,
-        params: Vec<(String, String)>, // (name, type)
+        return_type: Type,
+        params: Vec<(String, Type)>, // (name, type)
         body: Vec<Stmt>,
     },
     If {
@@ -101,11 +253,13 @@ pub enum Stmt {
     },
     Of {
         value: Expr,
-        cases: Vec<(Vec<Expr>, Vec<Stmt>)>, // Multiple expressions can map to same branch
+        cases: Vec<OfCase>, // Multiple expressions can map to same branch
         default: Option<Vec<Stmt>>,
     },
+    /// `change name[i][j] to value`; `indices` is empty for a plain reassignment.
     Change {
         name: String,
+        indices: Vec<Expr>,
         value: Expr,
     },
     Break,
@@ -115,8 +269,19 @@ pub enum Stmt {
         name: String,
         body: Vec<Stmt>,
     },
+    /// `rescue { body } catch error { handler }`
+    Rescue {
+        body: Vec<Stmt>,
+        error_name: String,
+        handler: Vec<Stmt>,
+    },
+    /// `see #Name is ...`; generic definitions such as `see #Pair(A, B) is ...`
+    /// list their type parameters, which the definition refers to as `#A`.
+    /// A parameter may name a default, as in `#Result(T, E = Any)`, used when
+    /// a use such as `#Result(Number)` leaves it out.
     TypeDefinition {
         name: String,
+        parameters: Vec<(String, Option<Type>)>,
         definition: TypeDefinition,
     },
     Import {
@@ -131,37 +296,225 @@ pub enum Stmt {
 pub enum TypeDefinition {
     Alias(Type),
     Record {
-        fields: Vec<(String, String)>, // field name, field type
+        fields: Vec<(String, Type)>, // field name, field type
     },
     Variant {
-        variants: Vec<(String, Vec<(String, String)>)>, // variant name, fields
+        variants: Vec<VariantCase>,
     },
 }
 
+/// A variant name and its `(field name, field type)` payload.
+pub type VariantCase = (String, Vec<(String, Type)>);
+
+/// Type annotations with a dedicated token, such as `#Number`.
+const BUILTIN_TYPES: &[Token] = &[
+    Token::NumberType, Token::IntegerType, Token::TextType, Token::DecisionType,
+    Token::NothingType, Token::BlissType, Token::AnyType,
+    Token::ListType, Token::MapType, Token::TupleType,
+];
+
 pub struct Parser {
-    tokens: Vec<Token>,
+    tokens: Vec<SpannedToken>,
     current: usize,
+    diagnostics: Vec<Diagnostic>,
+    /// Where an unterminated string starts. It runs to the end of the input,
+    /// so syntax errors past this point only echo it.
+    unterminated: Option<usize>,
 }
 
 impl Parser {
     pub fn new(input: &str) -> Self {
-        let lexer = crate::lexer::Lexer::new(input);
-        let tokens: Vec<Token> = lexer.collect();
+        Self::from_tokens(crate::lexer::Lexer::new(input))
+    }
+
+    fn from_tokens(lexer: impl IntoIterator<Item = SpannedToken>) -> Self {
+        let mut tokens: Vec<SpannedToken> = Vec::new();
+        let mut diagnostics = Vec::new();
+        let mut invalid: Option<(String, Span)> = None;
+        let mut unterminated = None;
+
+        for spanned in lexer {
+            if spanned.token == Token::UnclosedInterpolation {
+                diagnostics.push(
+                    Diagnostic::error(ErrorCode::UnclosedInterpolation, "Unclosed string interpolation", spanned.span)
+                        .with_primary_message("this '{' is never closed")
+                        .with_note("close the expression with '}', or write '\\{' for a literal brace"),
+                );
+                // Stand in for the string so the surrounding code still parses.
+                tokens.push(SpannedToken { token: Token::String(Vec::new()), span: spanned.span });
+                continue;
+            }
+            if let Token::Error(text) = &spanned.token {
+                if text.starts_with('"') {
+                    if let Some((run, span)) = invalid.take() {
+                        diagnostics.push(Self::lex_error(&run, span));
+                    }
+                    // The string still stands where an expression is expected.
+                    diagnostics.push(Self::lex_error(text, spanned.span));
+                    unterminated = Some(spanned.span.start);
+                    tokens.push(SpannedToken { token: Token::String(Vec::new()), span: spanned.span });
+                    continue;
+                }
+                if Self::is_integer_literal(text) {
+                    // 2^63 only fits once negated: it lexes as `i64::MIN` for
+                    // `unary` to pick up, and `primary` reports it on its own.
+                    // Anything larger keeps a stand-in so the code still parses.
+                    let token = if text.parse::<u64>() == Ok(i64::MIN.unsigned_abs()) {
+                        Token::Integer(i64::MIN)
+                    } else {
+                        diagnostics.push(Self::lex_error(text, spanned.span));
+                        Token::Integer(0)
+                    };
+                    tokens.push(SpannedToken { token, span: spanned.span });
+                    continue;
+                }
+            }
+            if let Token::Error(text) = spanned.token {
+                // Merge runs of adjacent bad characters into a single report.
+                match &mut invalid {
+                    Some((run, span)) if span.end == spanned.span.start => {
+                        run.push_str(&text);
+                        *span = span.to(spanned.span);
+                    }
+                    _ => {
+                        if let Some((run, span)) = invalid.take() {
+                            diagnostics.push(Self::lex_error(&run, span));
+                        }
+                        invalid = Some((text, spanned.span));
+                    }
+                }
+            } else {
+                tokens.push(spanned);
+            }
+        }
+        if let Some((run, span)) = invalid {
+            diagnostics.push(Self::lex_error(&run, span));
+        }
+
         Self {
             tokens,
             current: 0,
+            diagnostics,
+            unterminated,
+        }
+    }
+
+    fn is_integer_literal(text: &str) -> bool {
+        !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit())
+    }
+
+    fn literal_too_large(span: Span) -> Diagnostic {
+        Diagnostic::error(ErrorCode::IntegerOverflow, "Integer literal too large", span)
+            .with_primary_message("does not fit in a 64-bit integer")
+            .with_note("write it with a decimal point to use a #Number instead")
+    }
+
+    fn lex_error(text: &str, span: Span) -> Diagnostic {
+        if text.starts_with('"') {
+            Diagnostic::error(ErrorCode::UnterminatedString, "Unterminated string literal", span)
+                .with_primary_message("string starts here")
+                .with_note("strings must be closed with '\"'")
+        } else if Self::is_integer_literal(text) {
+            Self::literal_too_large(span)
+        } else {
+            Diagnostic::error(ErrorCode::InvalidCharacter, format!("Unexpected character '{}'", text), span)
+                .with_primary_message("not valid in Wittgenlang source")
+        }
+    }
+
+    /// Parses the whole input, recovering from syntax errors.
+    ///
+    /// Statements that fail to parse are left out of the returned AST, and
+    /// every error encountered along the way is returned alongside it.
+    pub fn parse(&mut self) -> (Vec<Stmt>, Vec<Diagnostic>) {
+        let mut statements = Vec::new();
+        while !self.is_at_end() {
+            if let Some(stmt) = self.recovering_declaration() {
+                statements.push(stmt);
+            }
+        }
+        let mut diagnostics = std::mem::take(&mut self.diagnostics);
+        if let Some(start) = self.unterminated {
+            diagnostics.retain(|diagnostic| {
+                diagnostic.code == ErrorCode::UnterminatedString || diagnostic.span().start < start
+            });
+        }
+        (statements, diagnostics)
+    }
+
+    fn recovering_declaration(&mut self) -> Option<Stmt> {
+        let start = self.current;
+        match self.declaration() {
+            Ok(stmt) => Some(stmt),
+            Err(diagnostic) => {
+                self.diagnostics.push(diagnostic);
+                self.synchronize(start);
+                None
+            }
         }
     }
 
-    pub fn parse(&mut self) -> Result<Vec<Stmt>, String> {
+    /// Parses statements up to (but not including) the closing `}` of a block.
+    fn block_statements(&mut self) -> Vec<Stmt> {
         let mut statements = Vec::new();
+        while !self.check(&Token::RightBrace) && !self.is_at_end() {
+            if let Some(stmt) = self.recovering_declaration() {
+                statements.push(stmt);
+            }
+        }
+        statements
+    }
+
+    /// Skips tokens after a syntax error until the next statement boundary:
+    /// a declaration keyword, the start of a `name #Type ...` declaration, or
+    /// the `}` closing the enclosing block.
+    fn synchronize(&mut self, start: usize) {
+        if self.current == start {
+            self.advance();
+        }
+
+        // Braces opened by the failed statement have to be closed before the
+        // enclosing block can resume.
+        let mut depth = self.tokens[start..self.current]
+            .iter()
+            .fold(0usize, |depth, t| match t.token {
+                Token::LeftBrace => depth + 1,
+                Token::RightBrace => depth.saturating_sub(1),
+                _ => depth,
+            });
+
         while !self.is_at_end() {
-            statements.push(self.declaration()?);
+            match self.peek() {
+                Token::LeftBrace => depth += 1,
+                Token::RightBrace if depth == 0 => return,
+                Token::RightBrace => {
+                    depth -= 1;
+                    self.advance();
+                    if depth == 0 {
+                        return;
+                    }
+                    continue;
+                }
+                _ if depth == 0 && self.at_statement_boundary() => return,
+                _ => {}
+            }
+            self.advance();
+        }
+    }
+
+    fn at_statement_boundary(&self) -> bool {
+        match self.peek() {
+            Token::See | Token::Import | Token::ForNow | Token::Module | Token::By
+            | Token::If | Token::Unless | Token::While | Token::For | Token::Of
+            | Token::Change | Token::Produce | Token::Write | Token::Break | Token::Continue
+            | Token::Rescue => true,
+            // `name #Type is ...` and `name #Type by { ... }`
+            Token::Identifier(_) => self.type_end(self.current + 1).is_some(),
+            _ => false,
         }
-        Ok(statements)
     }
 
-    fn declaration(&mut self) -> Result<Stmt, String> {
+    fn declaration(&mut self) -> Result<Stmt, Diagnostic> {
         if self.match_token(&Token::Module) {
             return self.module_declaration();
         }
@@ -175,45 +528,32 @@ impl Parser {
             return self.import_declaration();
         }
         if self.match_token(&Token::ForNow) {
+            if self.check(&Token::LeftParen) {
+                return self.destructuring(true);
+            }
             return self.variable_declaration(true);
         }
-        if self.match_token(&Token::TypePrefix) {
-            // This is for handling type annotations 
-            return self.type_annotation();
+        if self.check(&Token::LeftParen) && self.at_destructuring() {
+            return self.destructuring(false);
         }
         if self.match_token(&Token::Write) {
             return self.write_statement();
         }
         if let Token::Identifier(_) = self.peek() {
             // Look ahead to check if this is a function declaration with "by"
-            let next_pos = self.current + 1;
-            let next_next_pos = self.current + 2;
+            let type_end = self.type_end(self.current + 1);
             
-            if next_pos < self.tokens.len() && next_next_pos < self.tokens.len() &&
-               (matches!(self.tokens[next_pos], Token::TypePrefix | Token::NumberType | 
-                         Token::TextType | Token::DecisionType | Token::NothingType |
-                         Token::BlissType | Token::AnyType)) &&
-               matches!(self.tokens[next_next_pos], Token::By) {
+            if matches!(type_end.and_then(|end| self.tokens.get(end)), Some(SpannedToken { token: Token::By, .. })) {
                 // This is a function declaration: identifier type by { ... }
+                let start = self.peek_span();
                 let function_name = if let Token::Identifier(name) = self.peek() {
                     self.advance();
                     name
                 } else {
-                    return Err("Expected function name".to_string());
+                    return Err(self.error("Expected function name"));
                 };
                 
-                let return_type_token = self.advance(); // Consume the type token
-                
-                let return_type = if let Token::TypePrefix = return_type_token {
-                    if let Token::Identifier(type_name) = self.peek() {
-                        self.advance();
-                        type_name
-                    } else {
-                        return Err("Expected return type after '#'".to_string());
-                    }
-                } else {
-                    format!("{:?}", return_type_token)
-                };
+                let return_type = self.type_expr("Expected return type")?;
                 
                 self.advance(); // Consume the "by" token
                 
@@ -221,126 +561,42 @@ impl Parser {
                 
                 // Parse parameters (starting with @)
                 let mut params = Vec::new();
-                while self.match_token(&Token::ExclamationMark) {
-                    // Skip comments inside function body
-                    self.skip_comment_line();
-                    continue;
-                }
-                
-                while self.match_token(&Token::Ampersand) {
+                while self.match_token(&Token::At) {
                     let param_name = if let Token::Identifier(name) = self.peek() {
                         self.advance();
                         name
                     } else {
-                        return Err("Expected parameter name after '&'".to_string());
+                        return Err(self.error("Expected parameter name after '@'"));
                     };
                     
-                    let param_type = if self.match_token(&Token::TypePrefix) {
-                        if let Token::Identifier(type_name) = self.peek() {
-                            self.advance();
-                            type_name
-                        } else {
-                            return Err("Expected parameter type after '#'".to_string());
-                        }
-                    } else if self.match_any(&[
-                        Token::NumberType, Token::TextType, Token::DecisionType, 
-                        Token::NothingType, Token::BlissType, Token::AnyType
-                    ]) {
-                        format!("{:?}", self.previous())
-                    } else {
-                        return Err("Expected parameter type".to_string());
-                    };
+                    let param_type = self.type_expr("Expected parameter type")?;
                     
                     params.push((param_name, param_type));
                 }
                 
                 // Parse function body
-                let mut body = Vec::new();
-                while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                    body.push(self.declaration()?);
-                }
+                let body = self.block_statements();
                 
                 self.consume(&Token::RightBrace, "Expected '}' after function body")?;
                 
-                return Ok(Stmt::Function {
+                return Ok(Stmt::new(StmtKind::Function {
                     name: function_name,
                     return_type,
                     params,
                     body,
-                });
+                }, start.to(self.previous_span())));
             }
             
             // Check if this is a value declaration with "is"
-            if next_pos < self.tokens.len() && 
-               (matches!(self.tokens[next_pos], Token::TypePrefix | Token::NumberType | 
-                         Token::TextType | Token::DecisionType | Token::NothingType |
-                         Token::BlissType | Token::AnyType)) {
+            if type_end.is_some() {
                 return self.variable_declaration(false);
             }
         }
         self.statement()
     }
 
-    fn function(&mut self, kind: &str) -> Result<Stmt, String> {
-        let name = if let Token::Identifier(name) = self.peek() {
-            self.advance();
-            name
-        } else {
-            return Err(format!("Expect {} name.", kind));
-        };
-
-        // Add a dummy return type for backward compatibility
-        let return_type = "Any".to_string();
-
-        self.consume(&Token::LeftParen, &format!("Expect '(' after {} name.", kind))?;
-        let mut parameters = Vec::new();
-        if !self.check(&Token::RightParen) {
-            loop {
-                if parameters.len() >= 255 {
-                    return Err("Cannot have more than 255 parameters.".to_string());
-                }
-                if let Token::Identifier(param) = self.consume(&Token::Identifier("".to_string()), "Expect parameter name.")? {
-                    // Make each parameter a tuple with a dummy type for compatibility
-                    parameters.push((param, "Any".to_string()));
-                }
-                if !self.match_token(&Token::Comma) {
-                    break;
-                }
-            }
-        }
-        self.consume(&Token::RightParen, "Expect ')' after parameters.")?;
-        self.consume(&Token::LeftBrace, &format!("Expect '{{' before {} body.", kind))?;
-        let mut body = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            body.push(self.declaration()?);
-        }
-        self.consume(&Token::RightBrace, "Expect '}' after body.")?;
-        Ok(Stmt::Function { name, return_type, params: parameters, body })
-    }
-
-    fn var_declaration(&mut self) -> Result<Stmt, String> {
-        let name = if let Token::Identifier(name) = self.peek() {
-            self.advance();
-            name
-        } else {
-            return Err("Expect variable name.".to_string());
-        };
-
-        // For now, use a dummy type
-        let type_name = "Any".to_string();
-
-        // For now, always require an initializer (no optionals)
-        let initializer = self.expression()?;
-
-        Ok(Stmt::Value {
-            name,
-            type_name, 
-            initializer,
-            mutable: false,
-        })
-    }
-
-    fn statement(&mut self) -> Result<Stmt, String> {
+    fn statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.peek_span();
         if self.match_token(&Token::If) {
             return self.if_statement();
         }
@@ -360,29 +616,31 @@ impl Parser {
             return self.change_statement();
         }
         if self.match_token(&Token::Break) {
-            return Ok(Stmt::Break);
+            return Ok(Stmt::new(StmtKind::Break, start));
         }
         if self.match_token(&Token::Continue) {
-            return Ok(Stmt::Continue);
+            return Ok(Stmt::new(StmtKind::Continue, start));
         }
         if self.match_token(&Token::Produce) {
             return self.produce_statement();
         }
+        if self.match_token(&Token::Rescue) {
+            return self.rescue_statement();
+        }
         
         // Handle expression statement
         let expr = self.expression()?;
-        Ok(Stmt::Expression(expr))
+        let span = expr.span;
+        Ok(Stmt::new(StmtKind::Expression(expr), span))
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, String> {
+    fn if_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         let condition = self.expression()?;
         
         self.consume(&Token::LeftBrace, "Expected '{' after if condition")?;
         
-        let mut then_branch = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            then_branch.push(self.declaration()?);
-        }
+        let then_branch = self.block_statements();
         
         self.consume(&Token::RightBrace, "Expected '}' after if block")?;
         
@@ -394,10 +652,7 @@ impl Parser {
             } else {
                 self.consume(&Token::LeftBrace, "Expected '{' after else")?;
                 
-                let mut else_stmts = Vec::new();
-                while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                    else_stmts.push(self.declaration()?);
-                }
+                let else_stmts = self.block_statements();
                 
                 self.consume(&Token::RightBrace, "Expected '}' after else block")?;
                 
@@ -407,55 +662,52 @@ impl Parser {
             None
         };
         
-        Ok(Stmt::If {
+        Ok(Stmt::new(StmtKind::If {
             condition,
             then_branch,
             else_branch,
-        })
+        }, start.to(self.previous_span())))
     }
     
-    fn unless_statement(&mut self) -> Result<Stmt, String> {
+    fn unless_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         let condition = self.expression()?;
         
         self.consume(&Token::LeftBrace, "Expected '{' after unless condition")?;
         
-        let mut body = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            body.push(self.declaration()?);
-        }
+        let body = self.block_statements();
         
         self.consume(&Token::RightBrace, "Expected '}' after unless block")?;
         
-        Ok(Stmt::Unless {
+        Ok(Stmt::new(StmtKind::Unless {
             condition,
             body,
-        })
+        }, start.to(self.previous_span())))
     }
     
-    fn while_statement(&mut self) -> Result<Stmt, String> {
+    fn while_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         let condition = self.expression()?;
         
         self.consume(&Token::LeftBrace, "Expected '{' after while condition")?;
         
-        let mut body = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            body.push(self.declaration()?);
-        }
+        let body = self.block_statements();
         
         self.consume(&Token::RightBrace, "Expected '}' after while block")?;
         
-        Ok(Stmt::While {
+        Ok(Stmt::new(StmtKind::While {
             condition,
             body,
-        })
+        }, start.to(self.previous_span())))
     }
     
-    fn for_statement(&mut self) -> Result<Stmt, String> {
+    fn for_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         let variable = if let Token::Identifier(name) = self.peek() {
             self.advance();
             name
         } else {
-            return Err("Expected variable name after 'for'".to_string());
+            return Err(self.error("Expected variable name after 'for'"));
         };
         
         self.consume(&Token::In, "Expected 'in' after for loop variable")?;
@@ -464,21 +716,57 @@ impl Parser {
         
         self.consume(&Token::LeftBrace, "Expected '{' after for loop iterable")?;
         
-        let mut body = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            body.push(self.declaration()?);
-        }
+        let body = self.block_statements();
         
         self.consume(&Token::RightBrace, "Expected '}' after for loop body")?;
         
-        Ok(Stmt::For {
+        Ok(Stmt::new(StmtKind::For {
             variable,
             iterable,
             body,
-        })
+        }, start.to(self.previous_span())))
+    }
+    
+    fn rescue_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
+        
+        self.consume(&Token::LeftBrace, "Expected '{' after 'rescue'")?;
+        let body = self.block_statements();
+        self.consume(&Token::RightBrace, "Expected '}' after rescue block")?;
+        
+        self.consume(&Token::Catch, "Expected 'catch' after rescue block")?;
+        let error_name = if let Token::Identifier(name) = self.peek() {
+            self.advance();
+            name
+        } else {
+            return Err(self.error("Expected error name after 'catch'"));
+        };
+        
+        self.consume(&Token::LeftBrace, "Expected '{' after catch name")?;
+        let handler = self.block_statements();
+        self.consume(&Token::RightBrace, "Expected '}' after catch block")?;
+        
+        Ok(Stmt::new(StmtKind::Rescue {
+            body,
+            error_name,
+            handler,
+        }, start.to(self.previous_span())))
     }
     
-    fn of_statement(&mut self) -> Result<Stmt, String> {
+    fn of_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
+        let (value, cases, default) = self.of_body()?;
+        
+        Ok(Stmt::new(StmtKind::Of {
+            value,
+            cases,
+            default,
+        }, start.to(self.previous_span())))
+    }
+    
+    /// Parses everything after the `of` keyword, shared by the statement and
+    /// expression forms.
+    fn of_body(&mut self) -> Result<OfParts, Diagnostic> {
         // Parse the value to switch on
         let value = self.expression()?;
         
@@ -495,9 +783,7 @@ impl Parser {
                 let mut default_stmts = Vec::new();
                 if self.match_token(&Token::LeftBrace) {
                     // Multiple statements in default case
-                    while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                        default_stmts.push(self.declaration()?);
-                    }
+                    default_stmts.extend(self.block_statements());
                     self.consume(&Token::RightBrace, "Expected '}' after default case block")?;
                 } else {
                     // Single statement in default case
@@ -523,9 +809,7 @@ impl Parser {
                 let mut case_stmts = Vec::new();
                 if self.match_token(&Token::LeftBrace) {
                     // Multiple statements in case
-                    while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                        case_stmts.push(self.declaration()?);
-                    }
+                    case_stmts.extend(self.block_statements());
                     self.consume(&Token::RightBrace, "Expected '}' after case block")?;
                 } else {
                     // Single statement in case
@@ -538,132 +822,255 @@ impl Parser {
         
         self.consume(&Token::RightBrace, "Expected '}' after switch statement")?;
         
-        Ok(Stmt::Of {
-            value,
-            cases,
-            default,
-        })
+        Ok((value, cases, default))
     }
     
-    fn produce_statement(&mut self) -> Result<Stmt, String> {
+    fn produce_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         let value = if self.check(&Token::RightBrace) {
             None
         } else {
             Some(self.expression()?)
         };
         
-        Ok(Stmt::Produce(value))
+        Ok(Stmt::new(StmtKind::Produce(value), start.to(self.previous_span())))
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
+    fn expression(&mut self) -> Result<Expr, Diagnostic> {
         self.assignment()
     }
     
-    fn assignment(&mut self) -> Result<Expr, String> {
+    fn assignment(&mut self) -> Result<Expr, Diagnostic> {
+        if self.match_token(&Token::Try) {
+            return self.try_expression();
+        }
+        
         let expr = self.logic_or()?;
         
         if self.match_token(&Token::Is) {
-            if let Expr::Variable(name) = expr {
+            if let ExprKind::Variable(_) = expr.kind {
                 let value = self.assignment()?;
                 // In Wittgenlang, this would be a variable declaration without a type,
                 // but we'll handle it as an assignment for simplicity
-                return Ok(Expr::Binary {
-                    left: Box::new(Expr::Variable(name)),
+                let span = expr.span.to(value.span);
+                return Ok(Expr::new(ExprKind::Binary {
+                    left: Box::new(expr),
                     operator: Token::Is,
                     right: Box::new(value),
-                });
+                }, span));
             } else {
-                return Err("Invalid assignment target.".to_string());
+                return Err(Diagnostic::error(
+                    ErrorCode::InvalidAssignmentTarget,
+                    "Invalid assignment target.",
+                    expr.span,
+                ));
             }
         }
         
         Ok(expr)
     }
     
-    fn logic_or(&mut self) -> Result<Expr, String> {
+    fn try_expression(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.previous_span();
+        let operand = self.logic_or()?;
+        
+        // `| (error) -> ...` maps the error before it is returned
+        let handler = if self.match_token(&Token::Pipe) {
+            Some(Box::new(self.logic_or()?))
+        } else {
+            None
+        };
+        
+        let end = handler.as_ref().map_or(operand.span, |handler| handler.span);
+        Ok(Expr::new(ExprKind::Try {
+            operand: Box::new(operand),
+            handler,
+        }, start.to(end)))
+    }
+    
+    /// Parses `(a, b #Number) -> body`. Parameters without a type accept anything.
+    fn lambda(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.peek_span();
+        self.consume(&Token::LeftParen, "Expected '(' before lambda parameters")?;
+        
+        let mut params = Vec::new();
+        while !self.check(&Token::RightParen) && !self.is_at_end() {
+            let param_name = if let Token::Identifier(name) = self.peek() {
+                self.advance();
+                name
+            } else {
+                return Err(self.error("Expected parameter name"));
+            };
+            let param_type = if self.type_end(self.current).is_some() {
+                self.type_expr("Expected parameter type")?
+            } else {
+                Type::Primitive("Any".to_string())
+            };
+            params.push((param_name, param_type));
+            
+            if !self.match_token(&Token::Comma) {
+                break;
+            }
+        }
+        
+        self.consume(&Token::RightParen, "Expected ')' after lambda parameters")?;
+        self.consume(&Token::Arrow, "Expected '->' after lambda parameters")?;
+        
+        let body = if self.match_token(&Token::LeftBrace) {
+            let statements = self.block_statements();
+            self.consume(&Token::RightBrace, "Expected '}' after lambda body")?;
+            statements
+        } else {
+            let expr = self.expression()?;
+            let span = expr.span;
+            vec![Stmt::new(StmtKind::Expression(expr), span)]
+        };
+        
+        Ok(Expr::new(ExprKind::Lambda {
+            params,
+            body,
+        }, start.to(self.previous_span())))
+    }
+    
+    /// Whether the `(` at the current token starts `(a, b) is ...`, that is,
+    /// whether it holds two or more names and its `)` is followed by `is`.
+    fn at_destructuring(&self) -> bool {
+        let Some(close) = self.closing_paren(self.current) else {
+            return false;
+        };
+        let names = &self.tokens[self.current + 1..close];
+        names.len() > 1
+            && names.iter().enumerate().all(|(i, spanned)| match spanned.token {
+                Token::Identifier(_) => i % 2 == 0,
+                Token::Comma => i % 2 == 1,
+                _ => false,
+            })
+            && self.tokens.get(close + 1).map(|spanned| &spanned.token) == Some(&Token::Is)
+    }
+    
+    /// Parses `(a, b) is expression`, once any `forNow` has been consumed.
+    fn destructuring(&mut self, mutable: bool) -> Result<Stmt, Diagnostic> {
+        let start = self.peek_span();
+        self.consume(&Token::LeftParen, "Expected '(' before names to bind")?;
+        let mut names = Vec::new();
+        loop {
+            if let Token::Identifier(name) = self.peek() {
+                self.advance();
+                names.push(name);
+            } else {
+                return Err(self.error("Expected a name to bind"));
+            }
+            if !self.match_token(&Token::Comma) {
+                break;
+            }
+        }
+        self.consume(&Token::RightParen, "Expected ')' after names to bind")?;
+        self.consume(&Token::Is, "Expected 'is' after names to bind")?;
+        let initializer = self.expression()?;
+        
+        let span = start.to(initializer.span);
+        Ok(Stmt::new(StmtKind::Destructure { names, initializer, mutable }, span))
+    }
+    
+    /// Whether the `(` at the current token opens lambda parameters, that is,
+    /// whether its matching `)` is followed by `->`.
+    fn at_lambda(&self) -> bool {
+        let mut depth = 0;
+        for (offset, spanned) in self.tokens[self.current..].iter().enumerate() {
+            match spanned.token {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return self.peek_ahead(offset + 1) == Some(&Token::Arrow);
+                    }
+                }
+                Token::EOF => return false,
+                _ => {}
+            }
+        }
+        false
+    }
+    
+    fn logic_or(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.logic_and()?;
         
-        while self.match_token(&Token::Pipe) {
+        while self.match_token(&Token::Or) {
             let operator = self.previous();
             let right = self.logic_and()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+            expr = Self::logical(expr, operator, right);
         }
         
         Ok(expr)
     }
     
-    fn logic_and(&mut self) -> Result<Expr, String> {
+    fn logic_and(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.equality()?;
         
-        while self.match_token(&Token::Ampersand) {
+        while self.match_token(&Token::And) {
             let operator = self.previous();
             let right = self.equality()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+            expr = Self::logical(expr, operator, right);
         }
         
         Ok(expr)
     }
     
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn equality(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.comparison()?;
         
-        while self.match_any(&[Token::EqualEqual, Token::NotEqual, Token::Is]) {
+        while self.match_any(&[Token::Equal, Token::EqualEqual, Token::NotEqual, Token::Is]) {
             let operator = self.previous();
             let right = self.comparison()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, operator, right);
         }
         
         Ok(expr)
     }
     
-    fn comparison(&mut self) -> Result<Expr, String> {
-        let mut expr = self.term()?;
+    fn comparison(&mut self) -> Result<Expr, Diagnostic> {
+        let mut expr = self.range()?;
         
         while self.match_any(&[
             Token::Less, Token::LessEqual, 
             Token::Greater, Token::GreaterEqual
         ]) {
             let operator = self.previous();
-            let right = self.term()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+            let right = self.range()?;
+            expr = Self::binary(expr, operator, right);
         }
         
         Ok(expr)
     }
     
-    fn term(&mut self) -> Result<Expr, String> {
+    fn range(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.term()?;
+        
+        if self.match_token(&Token::Range) {
+            let end = self.term()?;
+            let span = start.span.to(end.span);
+            return Ok(Expr::new(ExprKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+            }, span));
+        }
+        
+        Ok(start)
+    }
+    
+    fn term(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.factor()?;
         
         while self.match_any(&[Token::Plus, Token::Minus, Token::Ampersand]) {
             let operator = self.previous();
             let right = self.factor()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, operator, right);
         }
         
         Ok(expr)
     }
     
-    fn factor(&mut self) -> Result<Expr, String> {
+    fn factor(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.unary()?;
         
         while self.match_any(&[
@@ -672,76 +1079,97 @@ impl Parser {
         ]) {
             let operator = self.previous();
             let right = self.unary()?;
-            expr = Expr::Binary {
-                left: Box::new(expr),
-                operator,
-                right: Box::new(right),
-            };
+            expr = Self::binary(expr, operator, right);
         }
         
         Ok(expr)
     }
     
-    fn unary(&mut self) -> Result<Expr, String> {
-        if self.match_any(&[Token::Minus, Token::ExclamationMark]) {
+    fn unary(&mut self) -> Result<Expr, Diagnostic> {
+        if self.match_any(&[Token::Minus, Token::Not]) {
+            let start = self.previous_span();
             let operator = self.previous();
+            if operator == Token::Minus && self.peek() == Token::Integer(i64::MIN) {
+                self.advance();
+                let span = start.to(self.previous_span());
+                return Ok(Expr::new(ExprKind::Literal(Literal::Integer(i64::MIN)), span));
+            }
             let right = self.unary()?;
-            return Ok(Expr::Unary {
+            let span = start.to(right.span);
+            return Ok(Expr::new(ExprKind::Unary {
                 operator,
                 right: Box::new(right),
-            });
+            }, span));
         }
         
         self.call()
     }
     
-    fn call(&mut self) -> Result<Expr, String> {
+    fn call(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.primary()?;
         
         loop {
-            if self.match_token(&Token::LeftParen) {
+            // A `(` or `[` that starts a new line begins the next statement
+            // rather than calling or indexing the end of this one.
+            let same_line = self.peek_span().line == self.previous_span().line;
+            if same_line && self.match_token(&Token::LeftParen) {
                 // Function call
                 expr = self.finish_call(expr)?;
             } else if self.match_token(&Token::Apostrophe) {
                 // Type function call: object'function
                 if let Token::Identifier(function) = self.peek() {
                     self.advance();
-                    expr = Expr::TypeFunctionCall {
+                    if function == "with" && self.match_token(&Token::LeftBrace) {
+                        // Record update: record'with { field: value }
+                        let fields = self.record_field_values()?;
+                        let span = expr.span.to(self.previous_span());
+                        expr = Expr::new(ExprKind::RecordUpdate {
+                            record: Box::new(expr),
+                            fields,
+                        }, span);
+                        continue;
+                    }
+                    let span = expr.span.to(self.previous_span());
+                    expr = Expr::new(ExprKind::TypeFunctionCall {
                         object: Box::new(expr),
                         function,
-                    };
+                    }, span);
                 } else {
-                    return Err("Expected function name after apostrophe".to_string());
+                    return Err(self.error("Expected function name after apostrophe"));
                 }
-            } else if self.match_token(&Token::LeftBracket) {
+            } else if same_line && self.match_token(&Token::LeftBracket) {
                 // List or map access: list[index]
                 let index = self.expression()?;
                 self.consume(&Token::RightBracket, "Expected ']' after index")?;
                 
-                expr = Expr::AccessExpression {
+                let span = expr.span.to(self.previous_span());
+                expr = Expr::new(ExprKind::AccessExpression {
                     object: Box::new(expr),
                     index: Box::new(index),
-                };
+                }, span);
             } else if self.match_token(&Token::Dot) {
                 // No-argument function call: name.
-                if let Expr::Variable(name) = expr {
+                let span = expr.span.to(self.previous_span());
+                if let ExprKind::Variable(_) = expr.kind {
                     // Don't consume any more tokens - this is a no-args function call
-                    expr = Expr::FunctionCall {
-                        name,
+                    expr = Expr::new(ExprKind::FunctionCall {
+                        callee: Box::new(expr),
                         arguments: Vec::new(),
                         named_arguments: Vec::new(),
-                    };
+                    }, span);
                 } else {
                     // Method call with no arguments: object.method
                     if let Token::Identifier(method) = self.peek() {
                         self.advance();
-                        expr = Expr::FunctionCall {
-                            name: method,
+                        let method_span = self.previous_span();
+                        let span = expr.span.to(method_span);
+                        expr = Expr::new(ExprKind::FunctionCall {
+                            callee: Box::new(Expr::new(ExprKind::Variable(method), method_span)),
                             arguments: vec![expr],
                             named_arguments: Vec::new(),
-                        };
+                        }, span);
                     } else {
-                        return Err("Expected method name after dot".to_string());
+                        return Err(self.error("Expected method name after dot"));
                     }
                 }
             } else {
@@ -752,7 +1180,7 @@ impl Parser {
         Ok(expr)
     }
     
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, String> {
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, Diagnostic> {
         let mut arguments = Vec::new();
         let mut named_arguments = Vec::new();
         
@@ -766,7 +1194,7 @@ impl Parser {
                         let value = self.expression()?;
                         named_arguments.push((name, value));
                     } else {
-                        return Err("Expected parameter name for named argument".to_string());
+                        return Err(self.error("Expected parameter name for named argument"));
                     }
                 } else {
                     // Positional argument
@@ -781,54 +1209,92 @@ impl Parser {
         
         self.consume(&Token::RightParen, "Expected ')' after arguments")?;
         
-        let function_name = if let Expr::Variable(name) = callee {
-            name
-        } else {
-            return Err("Expected function name".to_string());
-        };
-        
-        Ok(Expr::FunctionCall {
-            name: function_name,
+        let span = callee.span.to(self.previous_span());
+        Ok(Expr::new(ExprKind::FunctionCall {
+            callee: Box::new(callee),
             arguments,
             named_arguments,
-        })
+        }, span))
     }
     
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.peek_span();
         if self.match_token(&Token::Yes) {
-            return Ok(Expr::Literal(Literal::Decision(true)));
+            return Ok(Expr::new(ExprKind::Literal(Literal::Decision(true)), start));
         }
         if self.match_token(&Token::No) {
-            return Ok(Expr::Literal(Literal::Decision(false)));
+            return Ok(Expr::new(ExprKind::Literal(Literal::Decision(false)), start));
         }
         if self.match_token(&Token::Nothing) {
-            return Ok(Expr::Literal(Literal::Nothing));
+            return Ok(Expr::new(ExprKind::Literal(Literal::Nothing), start));
         }
         
         if let Token::Number(n) = self.peek() {
             self.advance();
-            return Ok(Expr::Literal(Literal::Number(n)));
+            return Ok(Expr::new(ExprKind::Literal(Literal::Number(n)), start));
         }
         
-        if let Token::String(s) = self.peek() {
+        if let Token::Integer(i) = self.peek() {
             self.advance();
-            return Ok(Expr::Literal(Literal::String(s)));
+            if i == i64::MIN {
+                self.diagnostics.push(Self::literal_too_large(start));
+            }
+            return Ok(Expr::new(ExprKind::Literal(Literal::Integer(i)), start));
         }
         
-        if let Token::MultilineString(s) = self.peek() {
+        if let Token::String(parts) = self.peek() {
             self.advance();
-            return Ok(Expr::Literal(Literal::String(s)));
+            return self.string_literal(parts, start);
         }
         
         if let Token::Identifier(name) = self.peek() {
             self.advance();
-            return Ok(Expr::Variable(name));
+            // `Person { name: ... }` builds a record. Type names are capitalised and
+            // the braces open with `field:`, so `if ready { ... }` and
+            // `if shape = Empty { ... }` still open a block.
+            if name.starts_with(|c: char| c.is_ascii_uppercase())
+                && self.check(&Token::LeftBrace)
+                && matches!(self.peek_ahead(1), Some(Token::Identifier(_)))
+                && self.peek_ahead(2) == Some(&Token::Colon)
+            {
+                self.advance();
+                let fields = self.record_field_values()?;
+                return Ok(Expr::new(ExprKind::Record {
+                    type_name: name,
+                    fields,
+                }, start.to(self.previous_span())));
+            }
+            return Ok(Expr::new(ExprKind::Variable(name), start));
+        }
+        
+        if self.match_token(&Token::Of) {
+            let (value, cases, default) = self.of_body()?;
+            return Ok(Expr::new(ExprKind::Of {
+                value: Box::new(value),
+                cases,
+                default,
+            }, start.to(self.previous_span())));
+        }
+        
+        if self.check(&Token::LeftParen) && self.at_lambda() {
+            return self.lambda();
         }
         
         if self.match_token(&Token::LeftParen) {
             let expr = self.expression()?;
-            self.consume(&Token::RightParen, "Expected ')' after expression")?;
-            return Ok(Expr::Grouping(Box::new(expr)));
+            if !self.match_token(&Token::Comma) {
+                self.consume(&Token::RightParen, "Expected ')' after expression")?;
+                return Ok(Expr::new(ExprKind::Grouping(Box::new(expr)), start.to(self.previous_span())));
+            }
+            let mut elements = vec![expr];
+            loop {
+                elements.push(self.expression()?);
+                if !self.match_token(&Token::Comma) {
+                    break;
+                }
+            }
+            self.consume(&Token::RightParen, "Expected ')' after tuple elements")?;
+            return Ok(Expr::new(ExprKind::Tuple(elements), start.to(self.previous_span())));
         }
         
         if self.match_token(&Token::LeftBracket) {
@@ -846,37 +1312,91 @@ impl Parser {
             }
             
             self.consume(&Token::RightBracket, "Expected ']' after list elements")?;
-            return Ok(Expr::List(elements));
+            return Ok(Expr::new(ExprKind::List(elements), start.to(self.previous_span())));
         }
         
         if self.match_token(&Token::LeftBrace) {
-            // Map literal or record
-            if let Some(Token::Identifier(_)) = self.peek_ahead(1) {
-                if let Some(Token::Colon) = self.peek_ahead(2) {
-                    // This is a map with string keys
-                    let mut entries = Vec::new();
-                    
-                    if !self.check(&Token::RightBrace) {
-                        loop {
-                            let key = self.expression()?;
-                            self.consume(&Token::Colon, "Expected ':' after map key")?;
-                            let value = self.expression()?;
-                            
-                            entries.push((key, value));
-                            
-                            if !self.match_token(&Token::Comma) {
-                                break;
-                            }
-                        }
-                    }
-                    
-                    self.consume(&Token::RightBrace, "Expected '}' after map entries")?;
-                    return Ok(Expr::Map(entries));
+            // Map literal: { key: value, ... }
+            let mut entries = Vec::new();
+            
+            while !self.check(&Token::RightBrace) {
+                let key = self.expression()?;
+                self.consume(&Token::Colon, "Expected ':' after map key")?;
+                let value = self.expression()?;
+                
+                entries.push((key, value));
+                
+                if !self.match_token(&Token::Comma) {
+                    break;
                 }
             }
+            
+            self.consume(&Token::RightBrace, "Expected '}' after map entries")?;
+            return Ok(Expr::new(ExprKind::Map(entries), start.to(self.previous_span())));
         }
         
-        Err("Expected expression".to_string())
+        Err(Diagnostic::error(ErrorCode::ExpectedExpression, "Expected expression", start))
+    }
+
+    /// Parses `name: value` pairs up to and including the closing `}`.
+    /// Commas between fields are optional.
+    fn record_field_values(&mut self) -> Result<Vec<(String, Expr)>, Diagnostic> {
+        let mut fields = Vec::new();
+        while !self.check(&Token::RightBrace) && !self.is_at_end() {
+            let field_name = if let Token::Identifier(name) = self.peek() {
+                self.advance();
+                name
+            } else {
+                return Err(self.error("Expected field name"));
+            };
+            self.consume(&Token::Colon, "Expected ':' after field name")?;
+            fields.push((field_name, self.expression()?));
+            self.match_token(&Token::Comma);
+        }
+        self.consume(&Token::RightBrace, "Expected '}' after record fields")?;
+        Ok(fields)
+    }
+
+    /// Builds a string literal, parsing each embedded `{expression}` on its own.
+    fn string_literal(&mut self, parts: Vec<StringPart>, span: Span) -> Result<Expr, Diagnostic> {
+        if let [StringPart::Text(text)] = parts.as_slice() {
+            return Ok(Expr::new(ExprKind::Literal(Literal::String(text.clone())), span));
+        }
+        let mut pieces = Vec::new();
+        for part in parts {
+            match part {
+                StringPart::Text(text) => pieces.push(Expr::new(ExprKind::Literal(Literal::String(text)), span)),
+                StringPart::Code(tokens) => {
+                    let mut parser = Parser::from_tokens(tokens);
+                    let expr = parser.expression();
+                    self.diagnostics.append(&mut parser.diagnostics);
+                    let expr = expr?;
+                    if !parser.is_at_end() {
+                        return Err(parser.error("Expected '}' after interpolated expression"));
+                    }
+                    pieces.push(expr);
+                }
+            }
+        }
+        Ok(Expr::new(ExprKind::Interpolation(pieces), span))
+    }
+
+    fn logical(left: Expr, operator: Token, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
+        Expr::new(ExprKind::Logical {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }, span)
+    }
+
+    fn binary(left: Expr, operator: Token, right: Expr) -> Expr {
+        let span = left.span.to(right.span);
+        Expr::new(ExprKind::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }, span)
     }
 
     fn match_token(&mut self, token: &Token) -> bool {
@@ -914,179 +1434,177 @@ impl Parser {
     }
 
     fn is_at_end(&self) -> bool {
-        self.current >= self.tokens.len() || matches!(self.tokens.get(self.current), Some(Token::EOF) | None)
+        self.current >= self.tokens.len() || matches!(self.tokens.get(self.current).map(|t| &t.token), Some(Token::EOF) | None)
     }
 
     fn peek(&self) -> Token {
         if self.current >= self.tokens.len() {
             Token::EOF
         } else {
-            self.tokens[self.current].clone()
+            self.tokens[self.current].token.clone()
         }
     }
 
     fn previous(&self) -> Token {
-        self.tokens[self.current - 1].clone()
+        self.tokens[self.current - 1].token.clone()
+    }
+
+    fn peek_span(&self) -> Span {
+        self.tokens
+            .get(self.current)
+            .or_else(|| self.tokens.last())
+            .map(|t| t.span)
+            .unwrap_or_default()
+    }
+
+    fn previous_span(&self) -> Span {
+        if self.current > 0 {
+            self.tokens[self.current - 1].span
+        } else {
+            self.peek_span()
+        }
     }
 
-    fn consume(&mut self, token: &Token, message: &str) -> Result<Token, String> {
+    fn error(&self, message: &str) -> Diagnostic {
+        self.error_at(self.peek_span(), message)
+    }
+
+    fn error_at(&self, span: Span, message: &str) -> Diagnostic {
+        Diagnostic::error(ErrorCode::UnexpectedToken, message, span)
+    }
+
+    fn consume(&mut self, token: &Token, message: &str) -> Result<Token, Diagnostic> {
         if self.check(token) {
             Ok(self.advance())
         } else {
-            Err(message.to_string())
+            Err(self.error(message))
         }
     }
 
-    fn module_declaration(&mut self) -> Result<Stmt, String> {
+    fn module_declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         let name = if let Token::Identifier(name) = self.peek() {
             self.advance();
             name
         } else {
-            return Err("Expected module name".to_string());
+            return Err(self.error("Expected module name"));
         };
 
         self.consume(&Token::LeftBrace, "Expected '{' after module name")?;
         
-        let mut body = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            body.push(self.declaration()?);
-        }
+        let body = self.block_statements();
         
         self.consume(&Token::RightBrace, "Expected '}' after module body")?;
         
-        Ok(Stmt::ModuleDeclaration { name, body })
+        Ok(Stmt::new(StmtKind::ModuleDeclaration { name, body }, start.to(self.previous_span())))
     }
 
-    fn function_declaration(&mut self) -> Result<Stmt, String> {
+    fn function_declaration(&mut self) -> Result<Stmt, Diagnostic> {
         // This method is called when we encounter a bare 'by' token.
         // In our updated grammar, we should handle functions through the new pattern
         // in the declaration method, so this is just a fallback.
+        let start = self.previous_span();
         
         // Get the function name. It should have been consumed before the 'by' token.
         let name = if let Token::Identifier(name) = self.previous_token() {
             name
         } else {
-            return Err("Expected function name before 'by'".to_string());
+            return Err(self.error_at(start, "Expected function name before 'by'"));
         };
         
         // The return type should have already been parsed
-        let return_type = "Any".to_string(); // Default to Any
+        let return_type = Type::Primitive("Any".to_string()); // Default to Any
         
         self.consume(&Token::LeftBrace, "Expected '{' after 'by'")?;
         
         // Parse parameters (starting with @)
         let mut params = Vec::new();
-        while self.match_token(&Token::ExclamationMark) {
-            // Skip comments inside function body
-            self.skip_comment_line();
-            continue;
-        }
-        
-        while self.match_token(&Token::Ampersand) {
+        while self.match_token(&Token::At) {
             let param_name = if let Token::Identifier(name) = self.peek() {
                 self.advance();
                 name
             } else {
-                return Err("Expected parameter name after '@'".to_string());
+                return Err(self.error("Expected parameter name after '@'"));
             };
             
-            let param_type = if self.match_token(&Token::TypePrefix) {
-                if let Token::Identifier(type_name) = self.peek() {
-                    self.advance();
-                    type_name
-                } else {
-                    return Err("Expected field type after '#'".to_string());
-                }
-            } else if self.match_any(&[
-                Token::NumberType, Token::TextType, Token::DecisionType, 
-                Token::NothingType, Token::BlissType, Token::AnyType
-            ]) {
-                format!("{:?}", self.previous())
-            } else {
-                return Err("Expected parameter type".to_string());
-            };
+            let param_type = self.type_expr("Expected parameter type")?;
             
             params.push((param_name, param_type));
         }
         
         // Parse function body
-        let mut body = Vec::new();
-        while !self.check(&Token::RightBrace) && !self.is_at_end() {
-            body.push(self.declaration()?);
-        }
+        let body = self.block_statements();
         
         self.consume(&Token::RightBrace, "Expected '}' after function body")?;
         
-        Ok(Stmt::Function {
+        Ok(Stmt::new(StmtKind::Function {
             name,
             return_type,
             params,
             body,
-        })
+        }, start.to(self.previous_span())))
     }
     
-    fn variable_declaration(&mut self, mutable: bool) -> Result<Stmt, String> {
+    fn variable_declaration(&mut self, mutable: bool) -> Result<Stmt, Diagnostic> {
+        let start = if mutable { self.previous_span() } else { self.peek_span() };
         // Parse variable name
         let name = if let Token::Identifier(name) = self.peek() {
             self.advance();
             name
         } else {
-            return Err("Expected variable name".to_string());
+            return Err(self.error("Expected variable name"));
         };
         
         // Parse type annotation
-        let type_name = if self.match_token(&Token::TypePrefix) {
-            if let Token::Identifier(type_name) = self.peek() {
-                self.advance();
-                type_name
-            } else {
-                return Err("Expected type name after '#'".to_string());
-            }
-        } else if self.match_any(&[
-            Token::NumberType, Token::TextType, Token::DecisionType, 
-            Token::NothingType, Token::BlissType, Token::AnyType
-        ]) {
-            format!("{:?}", self.previous())
-        } else {
-            return Err("Expected type annotation for variable".to_string());
-        };
+        let value_type = self.type_expr("Expected type annotation for variable")?;
         
         // Parse initializer (required in Wittgenlang)
         self.consume(&Token::Is, "Expected 'is' after type annotation")?;
         let initializer = self.expression()?;
         
-        Ok(Stmt::Value {
+        let span = start.to(initializer.span);
+        Ok(Stmt::new(StmtKind::Value {
             name,
-            type_name,
+            value_type,
             initializer,
             mutable,
-        })
+        }, span))
     }
     
-    fn change_statement(&mut self) -> Result<Stmt, String> {
+    fn change_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         // Parse "change variable to newValue"
         let name = if let Token::Identifier(name) = self.peek() {
             self.advance();
             name
         } else {
-            return Err("Expected variable name after 'change'".to_string());
+            return Err(self.error("Expected variable name after 'change'"));
         };
         
+        let mut indices = Vec::new();
+        while self.match_token(&Token::LeftBracket) {
+            indices.push(self.expression()?);
+            self.consume(&Token::RightBracket, "Expected ']' after index")?;
+        }
+        
         self.consume(&Token::To, "Expected 'to' after variable name in change statement")?;
         let value = self.expression()?;
         
-        Ok(Stmt::Change { name, value })
+        let span = start.to(value.span);
+        Ok(Stmt::new(StmtKind::Change { name, indices, value }, span))
     }
     
-    fn write_statement(&mut self) -> Result<Stmt, String> {
+    fn write_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         self.consume(&Token::LeftParen, "Expected '(' after 'write'")?;
         let expr = self.expression()?;
         self.consume(&Token::RightParen, "Expected ')' after write expression")?;
         
-        Ok(Stmt::Write(expr))
+        Ok(Stmt::new(StmtKind::Write(expr), start.to(self.previous_span())))
     }
     
-    fn type_definition(&mut self) -> Result<Stmt, String> {
+    fn type_definition(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         // Parse "#TypeName is ..." type definition
         self.consume(&Token::TypePrefix, "Expected '#' after 'see'")?;
         
@@ -1094,128 +1612,287 @@ impl Parser {
             self.advance();
             name
         } else {
-            return Err("Expected type name".to_string());
+            return Err(self.error("Expected type name"));
         };
         
+        let mut parameters = Vec::new();
+        if self.match_token(&Token::LeftParen) {
+            loop {
+                if let Token::Identifier(parameter) = self.peek() {
+                    self.advance();
+                    let default = if self.match_token(&Token::Equal) {
+                        Some(self.type_argument()?)
+                    } else {
+                        None
+                    };
+                    parameters.push((parameter, default));
+                } else {
+                    return Err(self.error("Expected type parameter name"));
+                }
+                if !self.match_token(&Token::Comma) {
+                    break;
+                }
+            }
+            self.consume(&Token::RightParen, "Expected ')' after type parameters")?;
+        }
+        
         self.consume(&Token::Is, "Expected 'is' after type name")?;
         
-        let definition = if self.match_token(&Token::TypePrefix) {
-            // Alias type: #AliasType is #ExistingType
-            if let Token::Identifier(target_type) = self.peek() {
-                self.advance();
-                TypeDefinition::Alias(Type::Primitive(target_type))
-            } else {
-                return Err("Expected type name after '#'".to_string());
-            }
+        let keyword = match (self.peek(), self.peek_ahead(1)) {
+            (Token::TypePrefix, Some(Token::Identifier(name))) => Some(name.clone()),
+            _ => None,
+        };
+        let definition = if keyword.as_deref() == Some("Record") {
+            // Record type: #Person is #Record { name #Text age #Number }
+            self.advance();
+            self.advance();
+            self.consume(&Token::LeftBrace, "Expected '{' after '#Record'")?;
+            TypeDefinition::Record { fields: self.record_fields()? }
+        } else if keyword.as_deref() == Some("Variant") {
+            // Variant type: #Shape is #Variant { Circle(radius #Number) Square(side #Number) }
+            self.advance();
+            self.advance();
+            self.consume(&Token::LeftBrace, "Expected '{' after '#Variant'")?;
+            TypeDefinition::Variant { variants: self.variant_cases()? }
+        } else if self.type_end(self.current).is_some() {
+            // Alias type: #Age is #Number
+            TypeDefinition::Alias(self.type_expr("Expected a type")?)
         } else if self.match_token(&Token::Record) {
             // Record type: #Person is record { name #Text, age #Number }
             self.consume(&Token::LeftBrace, "Expected '{' after 'record'")?;
-            
-            let mut fields = Vec::new();
-            while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                let field_name = if let Token::Identifier(name) = self.peek() {
-                    self.advance();
-                    name
-                } else {
-                    return Err("Expected field name".to_string());
-                };
-                
-                let field_type = if self.match_token(&Token::TypePrefix) {
-                    if let Token::Identifier(type_name) = self.peek() {
-                        self.advance();
-                        type_name
-                    } else {
-                        return Err("Expected field type after '#'".to_string());
+            TypeDefinition::Record { fields: self.record_fields()? }
+        } else if self.match_token(&Token::Variant) {
+            // Variant type: #Shape is variant { Circle(radius #Number), Rectangle(width #Number, height #Number) }
+            self.consume(&Token::LeftBrace, "Expected '{' after 'variant'")?;
+
+            TypeDefinition::Variant { variants: self.variant_cases()? }
+        } else {
+            return Err(self.error("Expected 'record', 'variant', or '#' after 'is' in type definition"));
+        };
+        
+        Ok(Stmt::new(StmtKind::TypeDefinition { name: type_name, parameters, definition }, start.to(self.previous_span())))
+    }
+    
+    /// Parses a type annotation: `#Number`, `#List(Text)`, `#Map(Text, Number)`,
+    /// `#Pair(A, B)`, `#(Number) -> #Number` or a union such as `#A | #B`.
+    /// `#T*` is shorthand for `#Optional(T)`.
+    fn type_expr(&mut self, message: &str) -> Result<Type, Diagnostic> {
+        let first = self.single_type(message)?;
+        if !self.check(&Token::Pipe) {
+            return Ok(first);
+        }
+        let mut options = vec![first];
+        while self.match_token(&Token::Pipe) {
+            options.push(self.single_type("Expected a type after '|'")?);
+        }
+        Ok(Type::Union(options))
+    }
+    
+    fn single_type(&mut self, message: &str) -> Result<Type, Diagnostic> {
+        let ty = if self.match_token(&Token::TypePrefix) {
+            if self.match_token(&Token::LeftParen) {
+                let parameters = self.type_arguments()?;
+                self.consume(&Token::Arrow, "Expected '->' after function parameter types")?;
+                let return_type = self.single_type("Expected return type after '->'")?;
+                return Ok(Type::Function {
+                    parameters,
+                    return_type: Box::new(return_type),
+                });
+            }
+            if let Token::Identifier(name) = self.peek() {
+                self.advance();
+                self.named_type(name)?
+            } else {
+                return Err(self.error("Expected type name after '#'"));
+            }
+        } else if self.match_any(BUILTIN_TYPES) {
+            // The `NumberType` token names the type `Number`
+            let name = format!("{:?}", self.previous());
+            self.named_type(name.trim_end_matches("Type").to_string())?
+        } else {
+            return Err(self.error(message));
+        };
+        
+        if self.match_token(&Token::Star) {
+            Ok(Type::optional(ty))
+        } else {
+            Ok(ty)
+        }
+    }
+    
+    /// `Name` or `Name(arguments)`, once any leading `#` has been consumed.
+    /// The `(` must follow the name directly, so a parenthesised expression
+    /// after an annotation is not read as type arguments.
+    fn named_type(&mut self, name: String) -> Result<Type, Diagnostic> {
+        let attached = self.peek_span().start == self.previous_span().end;
+        let arguments = if attached && self.match_token(&Token::LeftParen) {
+            self.type_arguments()?
+        } else {
+            Vec::new()
+        };
+        Ok(Type::named(name, arguments))
+    }
+    
+    /// Parses comma-separated types up to and including the closing `)`.
+    /// Arguments may leave off their `#`, as in `#List(Number)`.
+    fn type_arguments(&mut self) -> Result<Vec<Type>, Diagnostic> {
+        let mut arguments = Vec::new();
+        if !self.check(&Token::RightParen) {
+            loop {
+                arguments.push(self.type_argument()?);
+                if !self.match_token(&Token::Comma) {
+                    break;
+                }
+            }
+        }
+        self.consume(&Token::RightParen, "Expected ')' after type arguments")?;
+        Ok(arguments)
+    }
+    
+    /// One type argument, which may leave off its `#`.
+    fn type_argument(&mut self) -> Result<Type, Diagnostic> {
+        if let Token::Identifier(name) = self.peek() {
+            self.advance();
+            let ty = self.named_type(name)?;
+            if self.match_token(&Token::Star) {
+                Ok(Type::optional(ty))
+            } else {
+                Ok(ty)
+            }
+        } else {
+            self.type_expr("Expected a type")
+        }
+    }
+    
+    /// If a type annotation starts at token `index`, returns the index just past
+    /// it. Mirrors `type_expr` without building anything.
+    fn type_end(&self, index: usize) -> Option<usize> {
+        let mut end = self.single_type_end(index)?;
+        while self.tokens.get(end).map(|spanned| &spanned.token) == Some(&Token::Pipe) {
+            end = self.single_type_end(end + 1)?;
+        }
+        Some(end)
+    }
+    
+    fn single_type_end(&self, index: usize) -> Option<usize> {
+        let token_at = |index: usize| self.tokens.get(index).map(|spanned| &spanned.token);
+        let mut end = match token_at(index)? {
+            Token::TypePrefix => match token_at(index + 1)? {
+                Token::Identifier(_) => index + 2,
+                Token::LeftParen => {
+                    let close = self.closing_paren(index + 1)?;
+                    if token_at(close + 1) != Some(&Token::Arrow) {
+                        return None;
+                    }
+                    return self.single_type_end(close + 2);
+                }
+                _ => return None,
+            },
+            token if BUILTIN_TYPES.contains(token) => index + 1,
+            _ => return None,
+        };
+        
+        let attached = self.tokens[end - 1].span.end == self.tokens.get(end).map_or(0, |spanned| spanned.span.start);
+        if attached && token_at(end) == Some(&Token::LeftParen) {
+            end = self.closing_paren(end)? + 1;
+        }
+        if token_at(end) == Some(&Token::Star) {
+            end += 1;
+        }
+        Some(end)
+    }
+    
+    /// The index of the `)` matching the `(` at `open`.
+    fn closing_paren(&self, open: usize) -> Option<usize> {
+        let mut depth = 0;
+        for (index, spanned) in self.tokens.iter().enumerate().skip(open) {
+            match spanned.token {
+                Token::LeftParen => depth += 1,
+                Token::RightParen => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(index);
                     }
-                } else if self.match_any(&[
-                    Token::NumberType, Token::TextType, Token::DecisionType, 
-                    Token::NothingType, Token::BlissType, Token::AnyType
-                ]) {
-                    format!("{:?}", self.previous())
-                } else {
-                    return Err("Expected field type".to_string());
-                };
-                
-                fields.push((field_name, field_type));
-                
-                if !self.check(&Token::RightBrace) {
-                    self.consume(&Token::Comma, "Expected ',' between record fields")?;
                 }
+                Token::EOF => return None,
+                _ => {}
             }
+        }
+        None
+    }
+    
+    /// Parses `name #Type` field declarations up to and including the closing `}`.
+    /// Commas between fields are optional.
+    fn record_fields(&mut self) -> Result<Vec<(String, Type)>, Diagnostic> {
+        let mut fields = Vec::new();
+        while !self.check(&Token::RightBrace) && !self.is_at_end() {
+            let field_name = if let Token::Identifier(name) = self.peek() {
+                self.advance();
+                name
+            } else {
+                return Err(self.error("Expected field name"));
+            };
             
-            self.consume(&Token::RightBrace, "Expected '}' after record fields")?;
+            let field_type = self.type_expr("Expected field type")?;
             
-            TypeDefinition::Record { fields }
-        } else if self.match_token(&Token::Variant) {
-            // Variant type: #Shape is variant { Circle(radius #Number), Rectangle(width #Number, height #Number) }
-            self.consume(&Token::LeftBrace, "Expected '{' after 'variant'")?;
+            fields.push((field_name, field_type));
             
-            let mut variants = Vec::new();
-            while !self.check(&Token::RightBrace) && !self.is_at_end() {
-                let variant_name = if let Token::Identifier(name) = self.peek() {
-                    self.advance();
-                    name
-                } else {
-                    return Err("Expected variant name".to_string());
-                };
-                
-                let mut fields = Vec::new();
-                if self.match_token(&Token::LeftParen) {
-                    // Parse variant fields
-                    if !self.check(&Token::RightParen) {
-                        loop {
-                            let field_name = if let Token::Identifier(name) = self.peek() {
-                                self.advance();
-                                name
-                            } else {
-                                return Err("Expected field name".to_string());
-                            };
-                            
-                            let field_type = if self.match_token(&Token::TypePrefix) {
-                                if let Token::Identifier(type_name) = self.peek() {
-                                    self.advance();
-                                    type_name
-                                } else {
-                                    return Err("Expected field type after '#'".to_string());
-                                }
-                            } else if self.match_any(&[
-                                Token::NumberType, Token::TextType, Token::DecisionType, 
-                                Token::NothingType, Token::BlissType, Token::AnyType
-                            ]) {
-                                format!("{:?}", self.previous())
-                            } else {
-                                return Err("Expected field type".to_string());
-                            };
-                            
-                            fields.push((field_name, field_type));
-                            
-                            if !self.match_token(&Token::Comma) {
-                                break;
-                            }
+            self.match_token(&Token::Comma);
+        }
+        
+        self.consume(&Token::RightBrace, "Expected '}' after record fields")?;
+        Ok(fields)
+    }
+    
+    /// Parses `Name(field #Type, ...)` cases up to and including the closing `}`.
+    /// Cases without fields may leave off the parentheses, and commas between
+    /// cases are optional.
+    fn variant_cases(&mut self) -> Result<Vec<VariantCase>, Diagnostic> {
+        let mut variants = Vec::new();
+        while !self.check(&Token::RightBrace) && !self.is_at_end() {
+            let variant_name = if let Token::Identifier(name) = self.peek() {
+                self.advance();
+                name
+            } else {
+                return Err(self.error("Expected variant name"));
+            };
+            
+            let mut fields = Vec::new();
+            if self.match_token(&Token::LeftParen) {
+                // Parse variant fields
+                if !self.check(&Token::RightParen) {
+                    loop {
+                        let field_name = if let Token::Identifier(name) = self.peek() {
+                            self.advance();
+                            name
+                        } else {
+                            return Err(self.error("Expected field name"));
+                        };
+                        
+                        let field_type = self.type_expr("Expected field type")?;
+                        
+                        fields.push((field_name, field_type));
+                        
+                        if !self.match_token(&Token::Comma) {
+                            break;
                         }
                     }
-                    
-                    self.consume(&Token::RightParen, "Expected ')' after variant fields")?;
                 }
                 
-                variants.push((variant_name, fields));
-                
-                if !self.check(&Token::RightBrace) {
-                    self.consume(&Token::Comma, "Expected ',' between variant definitions")?;
-                }
+                self.consume(&Token::RightParen, "Expected ')' after variant fields")?;
             }
             
-            self.consume(&Token::RightBrace, "Expected '}' after variant definitions")?;
+            variants.push((variant_name, fields));
             
-            TypeDefinition::Variant { variants }
-        } else {
-            return Err("Expected 'record', 'variant', or '#' after 'is' in type definition".to_string());
-        };
+            self.match_token(&Token::Comma);
+        }
         
-        Ok(Stmt::TypeDefinition { name: type_name, definition })
+        self.consume(&Token::RightBrace, "Expected '}' after variant definitions")?;
+        Ok(variants)
     }
     
-    fn import_declaration(&mut self) -> Result<Stmt, String> {
+    fn import_declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.previous_span();
         // Parse import statements: "import Math" or "import { add, subtract } from Math"
         
         let mut specific_imports = Vec::new();
@@ -1227,7 +1904,7 @@ impl Parser {
                     self.advance();
                     specific_imports.push(name);
                 } else {
-                    return Err("Expected function name in import list".to_string());
+                    return Err(self.error("Expected function name in import list"));
                 }
                 
                 if !self.match_token(&Token::Comma) {
@@ -1246,7 +1923,7 @@ impl Parser {
                 self.advance();
                 module_path.push(name);
             } else {
-                return Err("Expected module name".to_string());
+                return Err(self.error("Expected module name"));
             }
             
             if !self.match_token(&Token::Dot) {
@@ -1260,48 +1937,32 @@ impl Parser {
                 self.advance();
                 Some(name)
             } else {
-                return Err("Expected alias name after 'as'".to_string());
+                return Err(self.error("Expected alias name after 'as'"));
             }
         } else {
             None
         };
         
-        Ok(Stmt::Import {
+        Ok(Stmt::new(StmtKind::Import {
             module_path,
             specific_imports,
             alias,
-        })
-    }
-    
-    fn skip_comment_line(&mut self) {
-        while !self.is_at_end() && !self.check(&Token::RightBrace) {
-            self.advance();
-            if self.previous() == Token::ExclamationMark {
-                break;
-            }
-        }
+        }, start.to(self.previous_span())))
     }
     
     fn previous_token(&self) -> Token {
-        if self.current > 0 {
-            self.tokens[self.current - 2].clone()
-        } else {
-            Token::EOF
+        match self.current.checked_sub(2) {
+            Some(index) => self.tokens[index].token.clone(),
+            None => Token::EOF,
         }
     }
     
     fn peek_ahead(&self, offset: usize) -> Option<&Token> {
         let index = self.current + offset;
         if index < self.tokens.len() {
-            Some(&self.tokens[index])
+            Some(&self.tokens[index].token)
         } else {
             None
         }
     }
-
-    fn type_annotation(&mut self) -> Result<Stmt, String> {
-        // This is a placeholder - implement according to your language grammar
-        // For now, just parse it as a variable declaration
-        self.variable_declaration(false)
-    }
 } 
\ No newline at end of file
@@ -0,0 +1,1102 @@
+//! Static checks that run after parsing and before evaluation.
+//!
+//! The checker follows declared types through declarations, calls, returns
+//! and `of` switches. Expressions it cannot type precisely count as `#Any`,
+//! so it only reports conflicts between types it is sure of; the evaluator
+//! still checks everything else at runtime.
+
+use crate::diagnostic::{Diagnostic, ErrorCode};
+use crate::evaluator::PRELUDE;
+use crate::lexer::{Span, Token};
+use crate::parser::{Expr, ExprKind, Literal, OfCase, Parser, Stmt, StmtKind, Type, TypeDefinition};
+use std::collections::HashMap;
+
+/// Checks a parsed program and returns every type error found.
+pub fn check(statements: &[Stmt]) -> Vec<Diagnostic> {
+    TypeChecker::new().check(statements)
+}
+
+/// What a name declared with `see` stands for. The body of a generic
+/// definition refers to its type parameters as `#T`; each may have a default.
+#[derive(Clone)]
+struct Definition {
+    parameters: Vec<(String, Option<Type>)>,
+    kind: DefinitionKind,
+}
+
+#[derive(Clone)]
+enum DefinitionKind {
+    Record(Vec<(String, Type)>),
+    Variant(Vec<String>),
+    Alias(Type),
+}
+
+/// A name in scope. Functions and variant constructors also keep their
+/// parameters so named arguments can be checked, and constructors of generic
+/// variants the type parameters their parameters mention.
+#[derive(Clone)]
+struct Binding {
+    ty: Type,
+    params: Vec<(String, Type)>,
+    generics: Vec<String>,
+}
+
+/// Checks programs one after another, as the REPL feeds them in. Names and
+/// types declared by one program stay known to the next.
+#[derive(Clone)]
+pub struct TypeChecker {
+    scopes: Vec<HashMap<String, Binding>>,
+    definitions: HashMap<String, Definition>,
+    /// Variant tag -> (variant type, payload fields)
+    constructors: HashMap<String, (String, Vec<(String, Type)>)>,
+    /// Declared return types of the functions being checked, innermost last.
+    returns: Vec<Type>,
+    /// How many loops enclose the statement being checked, within the
+    /// innermost function.
+    loops: usize,
+    diagnostics: Vec<Diagnostic>,
+}
+
+fn any() -> Type {
+    Type::Primitive("Any".to_string())
+}
+
+fn primitive(name: &str) -> Type {
+    Type::Primitive(name.to_string())
+}
+
+fn is_primitive(ty: &Type, name: &str) -> bool {
+    matches!(ty, Type::Primitive(primitive) if primitive == name)
+}
+
+fn is_numeric(ty: &Type) -> bool {
+    is_primitive(ty, "Number") || is_primitive(ty, "Integer")
+}
+
+/// The name and type arguments of a record or variant type.
+fn nominal(ty: &Type) -> Option<(&str, &[Type])> {
+    match ty {
+        Type::Custom(name) => Some((name, &[])),
+        Type::Generic { name, arguments } => Some((name, arguments)),
+        _ => None,
+    }
+}
+
+/// Binds each type parameter to its argument. Missing arguments take the
+/// parameter's default, or `#Any` without one.
+fn instantiate(parameters: &[(String, Option<Type>)], arguments: &[Type]) -> HashMap<String, Type> {
+    parameters
+        .iter()
+        .enumerate()
+        .map(|(i, (parameter, default))| {
+            let argument = arguments.get(i).or(default.as_ref()).cloned().unwrap_or_else(any);
+            (parameter.clone(), argument)
+        })
+        .collect()
+}
+
+fn parameter_names(parameters: &[(String, Option<Type>)]) -> Vec<String> {
+    parameters.iter().map(|(name, _)| name.clone()).collect()
+}
+
+/// Learns what the `generics` in `expected` stand for from the type of a
+/// value passed for it. The first binding wins, except that an integer
+/// binding widens when a number shows up later.
+fn infer_parameters(expected: &Type, actual: &Type, generics: &[String], bindings: &mut HashMap<String, Type>) {
+    match (expected, actual) {
+        (Type::Custom(name), _) if generics.contains(name) => {
+            if is_primitive(actual, "Any") {
+                return;
+            }
+            match bindings.get(name) {
+                Some(bound) if !(is_primitive(bound, "Integer") && is_primitive(actual, "Number")) => {}
+                _ => {
+                    bindings.insert(name.clone(), actual.clone());
+                }
+            }
+        }
+        (Type::List(expected), Type::List(actual)) => infer_parameters(expected, actual, generics, bindings),
+        (Type::Map(ek, ev), Type::Map(ak, av)) => {
+            infer_parameters(ek, ak, generics, bindings);
+            infer_parameters(ev, av, generics, bindings);
+        }
+        (Type::Tuple(expected), Type::Tuple(actual))
+        | (Type::Generic { arguments: expected, .. }, Type::Generic { arguments: actual, .. }) => {
+            for (expected, actual) in expected.iter().zip(actual) {
+                infer_parameters(expected, actual, generics, bindings);
+            }
+        }
+        (
+            Type::Function { parameters: ep, return_type: er },
+            Type::Function { parameters: ap, return_type: ar },
+        ) => {
+            for (expected, actual) in ep.iter().zip(ap) {
+                infer_parameters(expected, actual, generics, bindings);
+            }
+            infer_parameters(er, ar, generics, bindings);
+        }
+        _ => {}
+    }
+}
+
+/// The type a generic definition produces once `bindings` are known.
+fn applied(name: &str, parameters: &[String], bindings: &HashMap<String, Type>) -> Type {
+    if parameters.is_empty() {
+        return Type::Custom(name.to_string());
+    }
+    Type::Generic {
+        name: name.to_string(),
+        arguments: parameters.iter().map(|parameter| bindings.get(parameter).cloned().unwrap_or_else(any)).collect(),
+    }
+}
+
+fn is_binding_name(name: &str) -> bool {
+    !name.starts_with(|c: char| c.is_ascii_uppercase())
+}
+
+impl TypeChecker {
+    pub fn new() -> Self {
+        let mut checker = Self {
+            scopes: vec![HashMap::new()],
+            definitions: HashMap::new(),
+            constructors: HashMap::new(),
+            returns: Vec::new(),
+            loops: 0,
+            diagnostics: Vec::new(),
+        };
+        let (prelude, _) = Parser::new(PRELUDE).parse();
+        checker.declare(&prelude);
+        checker.scopes.push(HashMap::new());
+        checker
+    }
+
+    /// Checks `statements` and returns every type error found. A program
+    /// with errors does not run, so its declarations are forgotten.
+    pub fn check(&mut self, statements: &[Stmt]) -> Vec<Diagnostic> {
+        let saved = self.clone();
+        self.check_block(statements);
+        let diagnostics = std::mem::take(&mut self.diagnostics);
+        if !diagnostics.is_empty() {
+            *self = saved;
+        }
+        diagnostics
+    }
+
+    fn define(&mut self, name: &str, ty: Type, params: Vec<(String, Type)>) {
+        self.define_generic(name, ty, params, Vec::new());
+    }
+
+    fn define_generic(&mut self, name: &str, ty: Type, params: Vec<(String, Type)>, generics: Vec<String>) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), Binding { ty, params, generics });
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding> {
+        self.scopes.iter().rev().find_map(|scope| scope.get(name))
+    }
+
+    /// Runs `check` in a fresh scope holding `bindings`.
+    fn scoped(&mut self, bindings: Vec<(String, Type)>, check: impl FnOnce(&mut Self)) {
+        self.scopes.push(HashMap::new());
+        for (name, ty) in bindings {
+            self.define(&name, ty, Vec::new());
+        }
+        check(self);
+        self.scopes.pop();
+    }
+
+    /// Follows aliases until reaching a type that is not one.
+    fn resolve(&self, ty: &Type) -> Type {
+        let mut ty = ty.clone();
+        // A bound on the chain length keeps alias cycles from looping forever
+        for _ in 0..32 {
+            let Some((name, arguments)) = nominal(&ty) else {
+                break;
+            };
+            match self.definitions.get(name) {
+                Some(Definition { parameters, kind: DefinitionKind::Alias(target) }) => {
+                    ty = target.substitute(&instantiate(parameters, arguments));
+                }
+                _ => break,
+            }
+        }
+        ty
+    }
+
+    /// The fields of record type `ty`, with its type arguments filled in.
+    fn record_fields(&self, ty: &Type) -> Option<Vec<(String, Type)>> {
+        let (name, arguments) = nominal(ty)?;
+        match self.definitions.get(name)? {
+            Definition { parameters, kind: DefinitionKind::Record(fields) } => {
+                let bindings = instantiate(parameters, arguments);
+                Some(fields.iter().map(|(field, ty)| (field.clone(), ty.substitute(&bindings))).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// The arguments of a use of generic type `name`, with defaults filled in
+    /// for any left off. A bare use stays bare.
+    fn full_arguments(&self, name: &str, arguments: &[Type]) -> Vec<Type> {
+        match self.definitions.get(name) {
+            Some(Definition { parameters, .. }) if !arguments.is_empty() && arguments.len() < parameters.len() => {
+                let bindings = instantiate(parameters, arguments);
+                parameters.iter().map(|(parameter, _)| bindings[parameter].clone()).collect()
+            }
+            _ => arguments.to_vec(),
+        }
+    }
+
+    /// Reports names that no `see` declares and generic types given the wrong
+    /// number of type arguments in an annotation. Returns whether the
+    /// annotation is sound.
+    fn check_annotation(&mut self, ty: &Type, span: Span) -> bool {
+        match ty {
+            Type::Primitive(_) => true,
+            Type::Custom(name) => self.check_defined(name, span),
+            Type::Generic { name, arguments } => {
+                let mut sound = self.check_all(arguments, span);
+                let Some(Definition { parameters, .. }) = self.definitions.get(name) else {
+                    return self.check_defined(name, span) & sound;
+                };
+                let most = parameters.len();
+                let least = parameters.iter().filter(|(_, default)| default.is_none()).count();
+                if arguments.len() < least || arguments.len() > most {
+                    let expected = if least == most {
+                        least.to_string()
+                    } else {
+                        format!("{} to {}", least, most)
+                    };
+                    self.diagnostics.push(Diagnostic::error(
+                        ErrorCode::InvalidTypeArguments,
+                        format!("#{} takes {} type argument(s) but was given {}.", name, expected, arguments.len()),
+                        span,
+                    ));
+                    sound = false;
+                }
+                sound
+            }
+            Type::List(element) => self.check_annotation(element, span),
+            Type::Map(key, value) => self.check_annotation(key, span) & self.check_annotation(value, span),
+            Type::Tuple(types) | Type::Union(types) => self.check_all(types, span),
+            Type::Function { parameters, return_type } => {
+                self.check_all(parameters, span) & self.check_annotation(return_type, span)
+            }
+        }
+    }
+
+    fn check_defined(&mut self, name: &str, span: Span) -> bool {
+        if self.definitions.contains_key(name) {
+            return true;
+        }
+        self.diagnostics.push(
+            Diagnostic::error(ErrorCode::UndefinedType, format!("Undefined type #{}.", name), span)
+                .with_note("types are declared with 'see'"),
+        );
+        false
+    }
+
+    fn check_all(&mut self, types: &[Type], span: Span) -> bool {
+        types.iter().fold(true, |sound, ty| self.check_annotation(ty, span) & sound)
+    }
+
+    /// Whether a value of type `actual` may be stored where `expected` is declared.
+    fn accepts(&self, expected: &Type, actual: &Type) -> bool {
+        let (expected, actual) = (self.resolve(expected), self.resolve(actual));
+        if is_primitive(&expected, "Any") || is_primitive(&actual, "Any") {
+            return true;
+        }
+        match (&expected, &actual) {
+            (Type::Primitive(e), Type::Primitive(a)) => e == a || (e == "Number" && a == "Integer"),
+            (Type::Union(options), _) => options.iter().any(|option| self.accepts(option, &actual)),
+            (_, Type::Union(options)) => options.iter().all(|option| self.accepts(&expected, option)),
+            (Type::List(e), Type::List(a)) => self.accepts(e, a),
+            (Type::Map(ek, ev), Type::Map(ak, av)) => self.accepts(ek, ak) && self.accepts(ev, av),
+            (Type::Tuple(e), Type::Tuple(a)) => {
+                e.len() == a.len() && e.iter().zip(a).all(|(e, a)| self.accepts(e, a))
+            }
+            (
+                Type::Function { parameters: ep, return_type: er },
+                Type::Function { parameters: ap, return_type: ar },
+            ) => {
+                ep.len() == ap.len()
+                    && ep.iter().zip(ap).all(|(e, a)| self.accepts(a, e))
+                    && self.accepts(er, ar)
+            }
+            _ => match (nominal(&expected), nominal(&actual)) {
+                // A bare `#Optional` accepts any `#Optional(T)` and the other way round
+                (Some((e, e_arguments)), Some((a, a_arguments))) => {
+                    let (e_arguments, a_arguments) = (self.full_arguments(e, e_arguments), self.full_arguments(a, a_arguments));
+                    e == a
+                        && (e_arguments.is_empty()
+                            || a_arguments.is_empty()
+                            || (e_arguments.len() == a_arguments.len()
+                                && e_arguments.iter().zip(&a_arguments).all(|(e, a)| self.accepts(e, a))))
+                }
+                _ => false,
+            },
+        }
+    }
+
+    /// Reports a mismatch when `actual` cannot be stored as `expected`.
+    fn expect(&mut self, expected: &Type, actual: &Type, span: Span, slot: &str) {
+        if !self.accepts(expected, actual) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    ErrorCode::TypeMismatch,
+                    format!("Expected {} for {} but found {}.", expected, slot, actual),
+                    span,
+                )
+                .with_primary_message(format!("this is {}", actual)),
+            );
+        }
+    }
+
+    /// Records the types and functions declared directly in `statements`, so
+    /// they can be used before the line that declares them.
+    fn declare(&mut self, statements: &[Stmt]) {
+        for stmt in statements {
+            match &stmt.kind {
+                StmtKind::TypeDefinition { name, parameters, definition } => {
+                    self.declare_type(name, parameters, definition)
+                }
+                StmtKind::Function { name, return_type, params, .. } => {
+                    let params: Vec<(String, Type)> = params
+                        .iter()
+                        .map(|(param, param_type)| (param.clone(), param_type.clone()))
+                        .collect();
+                    let ty = Type::Function {
+                        parameters: params.iter().map(|(_, ty)| ty.clone()).collect(),
+                        return_type: Box::new(return_type.clone()),
+                    };
+                    self.define(name, ty, params);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn declare_type(&mut self, name: &str, parameters: &[(String, Option<Type>)], definition: &TypeDefinition) {
+        let names = parameter_names(parameters);
+        let kind = match definition {
+            TypeDefinition::Alias(target) => DefinitionKind::Alias(target.clone()),
+            TypeDefinition::Record { fields } => DefinitionKind::Record(fields.clone()),
+            TypeDefinition::Variant { variants } => {
+                for (tag, fields) in variants {
+                    // A case without a payload, such as `None`, fits every instance
+                    let ty = if fields.is_empty() {
+                        Type::Custom(name.to_string())
+                    } else {
+                        let variables = names.iter().map(|parameter| Type::Custom(parameter.clone())).collect();
+                        Type::Function {
+                            parameters: fields.iter().map(|(_, ty)| ty.clone()).collect(),
+                            return_type: Box::new(Type::Generic { name: name.to_string(), arguments: variables }),
+                        }
+                    };
+                    self.define_generic(tag, ty, fields.clone(), names.clone());
+                    self.constructors.insert(tag.clone(), (name.to_string(), fields.clone()));
+                }
+                DefinitionKind::Variant(variants.iter().map(|(tag, _)| tag.clone()).collect())
+            }
+        };
+        let parameters = parameters.to_vec();
+        self.definitions.insert(name.to_string(), Definition { parameters, kind });
+    }
+
+    fn check_block(&mut self, statements: &[Stmt]) {
+        self.declare(statements);
+        for stmt in statements {
+            self.check_statement(stmt);
+        }
+    }
+
+    fn check_statement(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expression(expr) | StmtKind::Write(expr) => {
+                self.infer(expr);
+            }
+            StmtKind::Value { name, value_type, initializer, .. } => {
+                // A broken annotation has been reported; don't pile on
+                let declared = if self.check_annotation(value_type, stmt.span) { value_type.clone() } else { any() };
+                let actual = self.infer(initializer);
+                self.expect(&declared, &actual, initializer.span, &format!("'{}'", name));
+                // `#Any` says nothing, so keep whatever the initializer showed
+                let ty = if is_primitive(&declared, "Any") { actual } else { declared };
+                self.define(name, ty, Vec::new());
+            }
+            StmtKind::Destructure { names, initializer, .. } => {
+                let actual = self.infer_resolved(initializer);
+                let shape = Type::Tuple(vec![any(); names.len()]);
+                self.expect(&shape, &actual, initializer.span, &format!("'({})'", names.join(", ")));
+                for (i, name) in names.iter().enumerate() {
+                    let ty = match &actual {
+                        Type::Tuple(elements) => elements.get(i).cloned().unwrap_or_else(any),
+                        _ => any(),
+                    };
+                    self.define(name, ty, Vec::new());
+                }
+            }
+            StmtKind::Function { name, return_type, params, body } => {
+                self.check_annotation(return_type, stmt.span);
+                for (_, ty) in params {
+                    self.check_annotation(ty, stmt.span);
+                }
+                let params = params.iter().map(|(param, ty)| (param.clone(), ty.clone())).collect();
+                let return_type = return_type.clone();
+                self.scoped(params, |checker| checker.check_function_body(name, &return_type, body));
+            }
+            StmtKind::If { condition, then_branch, else_branch } => {
+                let mut bindings = Vec::new();
+                self.check_condition(condition, &mut bindings);
+                self.scoped(bindings, |checker| checker.check_block(then_branch));
+                if let Some(else_branch) = else_branch {
+                    self.scoped(Vec::new(), |checker| checker.check_block(else_branch));
+                }
+            }
+            StmtKind::Unless { condition, body } => {
+                self.infer(condition);
+                self.scoped(Vec::new(), |checker| checker.check_block(body));
+            }
+            StmtKind::While { condition, body } => {
+                self.infer(condition);
+                self.check_loop_body(Vec::new(), body);
+            }
+            StmtKind::For { variable, iterable, body } => {
+                let element = match (&iterable.kind, self.infer_resolved(iterable)) {
+                    (ExprKind::Range { .. }, _) => primitive("Integer"),
+                    (_, Type::List(element)) => *element,
+                    (_, Type::Map(key, value)) => Type::Tuple(vec![*key, *value]),
+                    (_, Type::Primitive(name)) if name == "Text" => primitive("Text"),
+                    _ => any(),
+                };
+                self.check_loop_body(vec![(variable.clone(), element)], body);
+            }
+            StmtKind::Of { value, cases, default } => {
+                self.check_of(value, cases, default.as_deref(), stmt.span);
+            }
+            StmtKind::Change { name, indices, value } => {
+                for index in indices {
+                    self.infer(index);
+                }
+                let actual = self.infer(value);
+                if indices.is_empty() {
+                    if let Some(declared) = self.lookup(name).map(|binding| binding.ty.clone()) {
+                        self.expect(&declared, &actual, value.span, &format!("'{}'", name));
+                    }
+                }
+            }
+            StmtKind::Produce(value) => {
+                let actual = value.as_ref().map(|value| (self.infer(value), value.span));
+                match (self.returns.last().cloned(), actual) {
+                    (None, _) => self.diagnostics.push(Diagnostic::error(
+                        ErrorCode::ProduceOutsideFunction,
+                        "Cannot use 'produce' outside of a function.",
+                        stmt.span,
+                    )),
+                    (Some(declared), Some((actual, span))) if !is_primitive(&declared, "Bliss") => {
+                        self.expect(&declared, &actual, span, "the produced value");
+                    }
+                    _ => {}
+                }
+            }
+            StmtKind::Break if self.loops == 0 => self.diagnostics.push(Diagnostic::error(
+                ErrorCode::BreakOutsideLoop,
+                "Cannot use 'break' outside of a loop.",
+                stmt.span,
+            )),
+            StmtKind::Continue if self.loops == 0 => self.diagnostics.push(Diagnostic::error(
+                ErrorCode::ContinueOutsideLoop,
+                "Cannot use 'continue' outside of a loop.",
+                stmt.span,
+            )),
+            StmtKind::ModuleDeclaration { body, .. } => {
+                self.scoped(Vec::new(), |checker| checker.check_block(body));
+            }
+            StmtKind::Rescue { body, error_name, handler } => {
+                self.scoped(Vec::new(), |checker| checker.check_block(body));
+                let error = vec![(error_name.clone(), Type::Custom("RuntimeError".to_string()))];
+                self.scoped(error, |checker| checker.check_block(handler));
+            }
+            StmtKind::TypeDefinition { parameters, definition, .. } => {
+                let mut annotations: Vec<&Type> = parameters.iter().filter_map(|(_, default)| default.as_ref()).collect();
+                match definition {
+                    TypeDefinition::Alias(target) => annotations.push(target),
+                    TypeDefinition::Record { fields } => annotations.extend(fields.iter().map(|(_, ty)| ty)),
+                    TypeDefinition::Variant { variants } => {
+                        annotations.extend(variants.iter().flat_map(|(_, fields)| fields.iter().map(|(_, ty)| ty)))
+                    }
+                }
+                // Inside the definition its type parameters name types too
+                let variables = parameters.iter().map(|(parameter, _)| (parameter.clone(), any())).collect();
+                for ty in annotations {
+                    self.check_annotation(&ty.substitute(&variables), stmt.span);
+                }
+            }
+            StmtKind::Break | StmtKind::Continue | StmtKind::Import { .. } => {}
+        }
+    }
+
+    /// Checks the body of a `for` or `while` loop, where `break` and
+    /// `continue` are allowed.
+    fn check_loop_body(&mut self, bindings: Vec<(String, Type)>, body: &[Stmt]) {
+        self.loops += 1;
+        self.scoped(bindings, |checker| checker.check_block(body));
+        self.loops -= 1;
+    }
+
+    /// Checks a function body; a trailing expression is the function's result.
+    /// Loops around the function do not reach into it.
+    fn check_function_body(&mut self, name: &str, return_type: &Type, body: &[Stmt]) {
+        let loops = std::mem::take(&mut self.loops);
+        self.returns.push(return_type.clone());
+        self.declare(body);
+        for (i, stmt) in body.iter().enumerate() {
+            match &stmt.kind {
+                StmtKind::Expression(expr) if i + 1 == body.len() => {
+                    let actual = self.infer(expr);
+                    if !is_primitive(return_type, "Bliss") {
+                        self.expect(return_type, &actual, expr.span, &format!("the result of '{}'", name));
+                    }
+                }
+                _ => self.check_statement(stmt),
+            }
+        }
+        self.returns.pop();
+        self.loops = loops;
+    }
+
+    /// Checks an `if` condition, collecting the names that `subject = Ctor(a)`
+    /// patterns bind for the then-branch.
+    fn check_condition(&mut self, condition: &Expr, bindings: &mut Vec<(String, Type)>) {
+        match &condition.kind {
+            ExprKind::Logical { left, operator: Token::And, right } => {
+                self.check_condition(left, bindings);
+                self.check_condition(right, bindings);
+            }
+            ExprKind::Binary { left, operator: Token::Equal, right } if self.is_constructor_pattern(right) => {
+                let subject = self.infer_resolved(left);
+                let _ = self.check_pattern(right, &subject, bindings);
+            }
+            _ => {
+                self.infer(condition);
+            }
+        }
+    }
+
+    fn is_constructor_pattern(&self, expr: &Expr) -> bool {
+        matches!(&expr.kind, ExprKind::FunctionCall { callee, .. }
+            if matches!(&callee.kind, ExprKind::Variable(tag) if self.constructors.contains_key(tag)))
+    }
+
+    /// Checks one `of` or `if` pattern against a value of type `subject`,
+    /// adding the names it binds. Returns the tag it matches whatever the
+    /// payload, or `Err` if it is malformed.
+    fn check_pattern(
+        &mut self,
+        pattern: &Expr,
+        subject: &Type,
+        bindings: &mut Vec<(String, Type)>,
+    ) -> Result<Option<String>, ()> {
+        match &pattern.kind {
+            ExprKind::FunctionCall { callee, arguments, .. } => {
+                if let ExprKind::Variable(tag) = &callee.kind {
+                    if let Some((type_name, fields)) = self.constructors.get(tag).cloned() {
+                        // Fields of a generic variant take the subject's type arguments
+                        let type_arguments = match nominal(subject) {
+                            Some((name, arguments)) if name == type_name => arguments.to_vec(),
+                            _ => Vec::new(),
+                        };
+                        let parameters = self.definitions.get(&type_name).map(|definition| definition.parameters.clone());
+                        let type_bindings = instantiate(&parameters.unwrap_or_default(), &type_arguments);
+                        if arguments.len() != fields.len() {
+                            self.diagnostics.push(Diagnostic::error(
+                                ErrorCode::InvalidPattern,
+                                format!("Pattern '{}' needs {} field(s) but names {}.", tag, fields.len(), arguments.len()),
+                                pattern.span,
+                            ));
+                            return Err(());
+                        }
+                        let mut covers_tag = true;
+                        for (argument, (_, field_type)) in arguments.iter().zip(fields) {
+                            let field_type = self.resolve(&field_type.substitute(&type_bindings));
+                            match &argument.kind {
+                                ExprKind::Variable(name) if is_binding_name(name) => bindings.push((name.clone(), field_type)),
+                                _ => {
+                                    covers_tag = false;
+                                    self.check_pattern(argument, &field_type, bindings)?;
+                                }
+                            }
+                        }
+                        return Ok(covers_tag.then(|| tag.clone()));
+                    }
+                }
+            }
+            ExprKind::Variable(tag) if self.constructors.get(tag).is_some_and(|(_, fields)| fields.is_empty()) => {
+                return Ok(Some(tag.clone()));
+            }
+            _ => {}
+        }
+        self.infer(pattern);
+        Ok(None)
+    }
+
+    /// Checks an `of` switch and returns the type of the value it yields.
+    /// Without an `otherwise` arm, a switch over a variant must handle every
+    /// case, and any other switch may match nothing and yield `nothing`.
+    fn check_of(&mut self, value: &Expr, cases: &[OfCase], default: Option<&[Stmt]>, span: Span) -> Type {
+        let subject = self.infer_resolved(value);
+        let mut covered = Vec::new();
+        let mut malformed = false;
+        let mut results = Vec::new();
+        for (patterns, body) in cases {
+            let mut bindings = Vec::new();
+            for pattern in patterns {
+                match self.check_pattern(pattern, &subject, &mut bindings) {
+                    Ok(Some(tag)) => covered.push(tag),
+                    Ok(None) => {}
+                    Err(()) => malformed = true,
+                }
+            }
+            self.scoped(bindings, |checker| results.extend(checker.check_arm(body)));
+        }
+        if let Some(default) = default {
+            self.scoped(Vec::new(), |checker| results.extend(checker.check_arm(default)));
+            return common_type(results);
+        }
+        let missing = self.missing_cases(&subject, &covered);
+        // A broken pattern has already been reported; don't pile on
+        match missing {
+            Some(missing) if !missing.is_empty() && !malformed => {
+                let type_name = nominal(&subject).map_or("", |(name, _)| name);
+                self.diagnostics.push(
+                    Diagnostic::error(
+                        ErrorCode::NonExhaustiveMatch,
+                        format!("'of' does not handle {} of #{}.", missing.join(", "), type_name),
+                        span,
+                    )
+                    .with_note("add an arm for each case or an 'otherwise' arm"),
+                );
+            }
+            Some(missing) if missing.is_empty() => {}
+            _ => results.push(primitive("Nothing")),
+        }
+        common_type(results)
+    }
+
+    /// The tags of variant type `subject` that `covered` leaves out, or `None`
+    /// when `subject` is not a variant.
+    fn missing_cases(&self, subject: &Type, covered: &[String]) -> Option<Vec<String>> {
+        let (type_name, _) = nominal(subject)?;
+        match self.definitions.get(type_name)? {
+            Definition { kind: DefinitionKind::Variant(tags), .. } => {
+                Some(tags.iter().filter(|tag| !covered.contains(tag)).cloned().collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Checks one arm of an `of` switch and returns the type of the value it
+    /// yields, if it yields one rather than leaving with `produce` or the like.
+    fn check_arm(&mut self, body: &[Stmt]) -> Option<Type> {
+        self.declare(body);
+        let Some((last, rest)) = body.split_last() else {
+            return Some(primitive("Nothing"));
+        };
+        for stmt in rest {
+            self.check_statement(stmt);
+        }
+        match &last.kind {
+            StmtKind::Expression(expr) => Some(self.infer(expr)),
+            StmtKind::Produce(_) | StmtKind::Break | StmtKind::Continue => {
+                self.check_statement(last);
+                None
+            }
+            _ => {
+                self.check_statement(last);
+                Some(any())
+            }
+        }
+    }
+
+    /// The type `expr` evaluates to, or `#Any` when it cannot be known here.
+    /// Checks calls and nested blocks along the way.
+    fn infer(&mut self, expr: &Expr) -> Type {
+        match &expr.kind {
+            ExprKind::Literal(literal) => match literal {
+                Literal::Number(_) => primitive("Number"),
+                Literal::Integer(_) => primitive("Integer"),
+                Literal::String(_) => primitive("Text"),
+                Literal::Decision(_) => primitive("Decision"),
+                Literal::Nothing => primitive("Nothing"),
+            },
+            ExprKind::Interpolation(pieces) => {
+                for piece in pieces {
+                    self.infer(piece);
+                }
+                primitive("Text")
+            }
+            ExprKind::Grouping(inner) => self.infer(inner),
+            ExprKind::Binary { left, operator: Token::Equal, right } if self.is_constructor_pattern(right) => {
+                let subject = self.infer_resolved(left);
+                let _ = self.check_pattern(right, &subject, &mut Vec::new());
+                primitive("Decision")
+            }
+            ExprKind::Binary { left, operator, right } => {
+                let left = self.infer_resolved(left);
+                let right = self.infer_resolved(right);
+                self.binary_type(operator, &left, &right)
+            }
+            ExprKind::Logical { left, right, .. } => {
+                self.infer(left);
+                self.infer(right);
+                primitive("Decision")
+            }
+            ExprKind::Unary { operator, right } => {
+                let operand = self.infer(right);
+                if *operator == Token::Not {
+                    primitive("Decision")
+                } else {
+                    operand
+                }
+            }
+            ExprKind::Variable(name) => match self.lookup(name) {
+                // Without arguments to go by, a generic constructor's type parameters are unknown
+                Some(binding) => {
+                    let unknown = binding.generics.iter().map(|generic| (generic.clone(), any())).collect();
+                    binding.ty.substitute(&unknown)
+                }
+                None => any(),
+            },
+            ExprKind::FunctionCall { callee, arguments, named_arguments } => {
+                self.check_call(callee, arguments, named_arguments, expr.span)
+            }
+            ExprKind::TypeFunctionCall { object, function } => {
+                let object = self.infer_resolved(object);
+                self.member_type(&object, function)
+            }
+            ExprKind::List(elements) => {
+                let types = elements.iter().map(|element| self.infer(element)).collect();
+                Type::List(Box::new(common_type(types)))
+            }
+            ExprKind::Tuple(elements) => Type::Tuple(elements.iter().map(|element| self.infer(element)).collect()),
+            ExprKind::Map(entries) => {
+                let (mut keys, mut values) = (Vec::new(), Vec::new());
+                for (key, value) in entries {
+                    keys.push(self.infer(key));
+                    values.push(self.infer(value));
+                }
+                Type::Map(Box::new(common_type(keys)), Box::new(common_type(values)))
+            }
+            ExprKind::Record { type_name, fields } => self.check_record(type_name, fields),
+            ExprKind::RecordUpdate { record, fields } => {
+                let record = self.infer(record);
+                let resolved = self.resolve(&record);
+                match (nominal(&resolved), self.record_fields(&resolved)) {
+                    (Some((type_name, _)), Some(declared)) => {
+                        let type_name = type_name.to_string();
+                        self.check_fields(&type_name, &declared, fields);
+                    }
+                    _ => {
+                        for (_, value) in fields {
+                            self.infer(value);
+                        }
+                    }
+                }
+                record
+            }
+            ExprKind::Lambda { params, body } => {
+                for (_, ty) in params {
+                    self.check_annotation(ty, expr.span);
+                }
+                let params: Vec<(String, Type)> =
+                    params.iter().map(|(param, ty)| (param.clone(), ty.clone())).collect();
+                let parameters = params.iter().map(|(_, ty)| ty.clone()).collect();
+                let mut return_type = any();
+                let loops = std::mem::take(&mut self.loops);
+                self.scoped(params, |checker| {
+                    checker.returns.push(any());
+                    checker.declare(body);
+                    for (i, stmt) in body.iter().enumerate() {
+                        match &stmt.kind {
+                            StmtKind::Expression(expr) if i + 1 == body.len() => return_type = checker.infer(expr),
+                            _ => checker.check_statement(stmt),
+                        }
+                    }
+                    checker.returns.pop();
+                });
+                self.loops = loops;
+                Type::Function { parameters, return_type: Box::new(return_type) }
+            }
+            ExprKind::AccessExpression { object, index } => {
+                let object = self.infer_resolved(object);
+                self.infer(index);
+                match object {
+                    _ if matches!(index.kind, ExprKind::Range { .. }) => object,
+                    Type::List(element) => *element,
+                    Type::Tuple(elements) => match index.kind {
+                        ExprKind::Literal(Literal::Integer(i)) => {
+                            usize::try_from(i).ok().and_then(|i| elements.get(i).cloned()).unwrap_or_else(any)
+                        }
+                        _ => any(),
+                    },
+                    Type::Map(_, value) => *value,
+                    Type::Primitive(name) if name == "Text" => primitive("Text"),
+                    _ => any(),
+                }
+            }
+            ExprKind::Range { start, end } => {
+                self.infer(start);
+                self.infer(end);
+                any()
+            }
+            ExprKind::Try { operand, handler } => {
+                let operand = self.infer_resolved(operand);
+                if let Some(handler) = handler {
+                    self.infer(handler);
+                }
+                match nominal(&operand) {
+                    Some(("Result", [success, ..])) => success.clone(),
+                    _ => any(),
+                }
+            }
+            ExprKind::Of { value, cases, default } => self.check_of(value, cases, default.as_deref(), expr.span),
+        }
+    }
+
+    fn infer_resolved(&mut self, expr: &Expr) -> Type {
+        let ty = self.infer(expr);
+        self.resolve(&ty)
+    }
+
+    fn binary_type(&self, operator: &Token, left: &Type, right: &Type) -> Type {
+        let integers = is_primitive(left, "Integer") && is_primitive(right, "Integer");
+        let numbers = is_numeric(left) && is_numeric(right);
+        match operator {
+            Token::Plus if is_primitive(left, "Text") && is_primitive(right, "Text") => primitive("Text"),
+            Token::Plus | Token::Minus | Token::Star | Token::IntegerDivide | Token::Modulo if integers => {
+                primitive("Integer")
+            }
+            Token::Plus | Token::Minus | Token::Star | Token::IntegerDivide | Token::Modulo | Token::Slash
+                if numbers =>
+            {
+                primitive("Number")
+            }
+            // A negative exponent turns two integers into a #Number
+            Token::Power if numbers && !integers => primitive("Number"),
+            Token::Ampersand => primitive("Text"),
+            Token::Equal
+            | Token::EqualEqual
+            | Token::NotEqual
+            | Token::Is
+            | Token::Greater
+            | Token::Less
+            | Token::GreaterEqual
+            | Token::LessEqual => primitive("Decision"),
+            _ => any(),
+        }
+    }
+
+    /// The type of `object'member`: a record field or a built-in conversion.
+    fn member_type(&self, object: &Type, member: &str) -> Type {
+        match (object, member) {
+            (_, "to-number") if is_numeric(object) => primitive("Number"),
+            (_, "to-integer") if is_numeric(object) => primitive("Integer"),
+            _ => self
+                .record_fields(object)
+                .and_then(|fields| fields.into_iter().find(|(field, _)| field == member))
+                .map_or_else(any, |(_, ty)| ty),
+        }
+    }
+
+    /// Checks arguments against the parameters of a known function or
+    /// constructor and returns the call's result type.
+    fn check_call(
+        &mut self,
+        callee: &Expr,
+        arguments: &[Expr],
+        named_arguments: &[(String, Expr)],
+        span: Span,
+    ) -> Type {
+        let (name, params, generics) = match &callee.kind {
+            ExprKind::Variable(name) => match self.lookup(name) {
+                Some(binding) => (name.clone(), binding.params.clone(), binding.generics.clone()),
+                None => (name.clone(), Vec::new(), Vec::new()),
+            },
+            _ => ("function".to_string(), Vec::new(), Vec::new()),
+        };
+        let callee_type = if generics.is_empty() {
+            self.infer_resolved(callee)
+        } else {
+            self.lookup(&name).map_or_else(any, |binding| binding.ty.clone())
+        };
+        let params = match (&callee_type, params.is_empty()) {
+            (Type::Function { parameters, .. }, true) => {
+                parameters.iter().map(|ty| (String::new(), ty.clone())).collect()
+            }
+            _ => params,
+        };
+        if matches!(callee_type, Type::Function { .. }) {
+            self.check_arguments_given(&name, callee.span, &params, arguments, named_arguments, span);
+        }
+
+        // Pair each argument with its parameter, then learn what a generic
+        // constructor's type parameters stand for before checking any of them
+        let mut checks = Vec::new();
+        for (i, argument) in arguments.iter().enumerate() {
+            let actual = self.infer(argument);
+            if let Some((param, expected)) = params.get(i) {
+                let slot = if param.is_empty() {
+                    format!("argument {} of '{}'", i + 1, name)
+                } else {
+                    format!("'{}' of '{}'", param, name)
+                };
+                checks.push((expected.clone(), actual, argument.span, slot));
+            }
+        }
+        for (param, argument) in named_arguments {
+            let actual = self.infer(argument);
+            if let Some((_, expected)) = params.iter().find(|(name, _)| name == param) {
+                checks.push((expected.clone(), actual, argument.span, format!("'{}' of '{}'", param, name)));
+            }
+        }
+        let mut type_bindings = HashMap::new();
+        for (expected, actual, _, _) in &checks {
+            infer_parameters(expected, actual, &generics, &mut type_bindings);
+        }
+        for parameter in &generics {
+            type_bindings.entry(parameter.clone()).or_insert_with(any);
+        }
+        for (expected, actual, span, slot) in checks {
+            self.expect(&expected.substitute(&type_bindings), &actual, span, &slot);
+        }
+
+        match callee_type {
+            Type::Function { return_type, .. } => return_type.substitute(&type_bindings),
+            _ => any(),
+        }
+    }
+
+    /// Reports a call that passes more arguments than `params`, names a
+    /// parameter twice or not at all, or leaves one out. The parameters of a
+    /// function value are known by position only.
+    fn check_arguments_given(
+        &mut self,
+        name: &str,
+        callee: Span,
+        params: &[(String, Type)],
+        arguments: &[Expr],
+        named_arguments: &[(String, Expr)],
+        span: Span,
+    ) {
+        if let Some(extra) = arguments.get(params.len()) {
+            self.diagnostics.push(
+                Diagnostic::error(
+                    ErrorCode::TooManyArguments,
+                    format!("'{}' takes {} argument(s) but {} were given.", name, params.len(), arguments.len()),
+                    extra.span,
+                )
+                .with_primary_message("unexpected argument")
+                .with_label(callee, format!("takes {} argument(s)", params.len())),
+            );
+            return;
+        }
+        if params.iter().any(|(param, _)| param.is_empty()) {
+            if named_arguments.is_empty() && arguments.len() < params.len() {
+                self.diagnostics.push(Diagnostic::error(
+                    ErrorCode::MissingArgument,
+                    format!("Missing argument {} of '{}'.", arguments.len() + 1, name),
+                    span,
+                ));
+            }
+            return;
+        }
+
+        let mut given: Vec<bool> = (0..params.len()).map(|i| i < arguments.len()).collect();
+        for (param, argument) in named_arguments {
+            let Some(position) = params.iter().position(|(name, _)| name == param) else {
+                self.diagnostics.push(Diagnostic::error(
+                    ErrorCode::UnknownArgument,
+                    format!("'{}' has no parameter named '{}'.", name, param),
+                    argument.span,
+                ));
+                return;
+            };
+            if given[position] {
+                self.diagnostics.push(Diagnostic::error(
+                    ErrorCode::DuplicateArgument,
+                    format!("Argument '{}' is given more than once.", param),
+                    argument.span,
+                ));
+                return;
+            }
+            given[position] = true;
+        }
+        if let Some(position) = given.iter().position(|given| !given) {
+            self.diagnostics.push(Diagnostic::error(
+                ErrorCode::MissingArgument,
+                format!("Missing argument for parameter '{}' of '{}'.", params[position].0, name),
+                span,
+            ));
+        }
+    }
+
+    /// Checks a record literal and returns its type. The type arguments of a
+    /// generic record come from the field values.
+    fn check_record(&mut self, type_name: &str, fields: &[(String, Expr)]) -> Type {
+        let Some(Definition { parameters, kind: DefinitionKind::Record(declared) }) = self.definitions.get(type_name)
+        else {
+            for (_, value) in fields {
+                self.infer(value);
+            }
+            return Type::Custom(type_name.to_string());
+        };
+        let (parameters, declared) = (parameter_names(parameters), declared.clone());
+
+        let mut type_bindings = HashMap::new();
+        let actual: Vec<Type> = fields.iter().map(|(_, value)| self.infer(value)).collect();
+        for ((field, _), actual) in fields.iter().zip(&actual) {
+            if let Some((_, expected)) = declared.iter().find(|(name, _)| name == field) {
+                infer_parameters(expected, actual, &parameters, &mut type_bindings);
+            }
+        }
+        let ty = applied(type_name, &parameters, &type_bindings);
+        let declared = self.record_fields(&ty).unwrap_or_default();
+        for ((field, value), actual) in fields.iter().zip(actual) {
+            if let Some((_, expected)) = declared.iter().find(|(name, _)| name == field) {
+                self.expect(expected, &actual, value.span, &format!("field '{}' of '{}'", field, type_name));
+            }
+        }
+        ty
+    }
+
+    /// Checks `name: value` fields against the declared record fields.
+    fn check_fields(&mut self, type_name: &str, declared: &[(String, Type)], fields: &[(String, Expr)]) {
+        for (field, value) in fields {
+            let actual = self.infer(value);
+            if let Some((_, expected)) = declared.iter().find(|(name, _)| name == field) {
+                self.expect(expected, &actual, value.span, &format!("field '{}' of '{}'", field, type_name));
+            }
+        }
+    }
+}
+
+impl Default for TypeChecker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The type every element shares, widening integers mixed with numbers.
+fn common_type(types: Vec<Type>) -> Type {
+    let Some(first) = types.first().cloned() else {
+        return any();
+    };
+    if types.iter().all(|ty| *ty == first) {
+        first
+    } else if types.iter().all(is_numeric) {
+        primitive("Number")
+    } else {
+        any()
+    }
+}
@@ -0,0 +1,41 @@
+use wittgenlang::diagnostic::{similar_name, Diagnostic, ErrorCode};
+use wittgenlang::lexer::Span;
+
+#[test]
+fn renders_rustc_style_snippet() {
+    let source = "one #Number is 1\nwrite (one / 0)";
+    let diagnostic = Diagnostic::error(ErrorCode::DivisionByZero, "Division by zero.", Span::new(24, 31, 2, 8))
+        .with_primary_message("divisor is zero")
+        .with_label(Span::new(0, 3, 1, 1), "declared here")
+        .with_note("numbers are never implicitly infinite")
+        .with_suggestion(Span::new(30, 31, 2, 14), "1", "use a non-zero divisor");
+
+    assert_eq!(
+        diagnostic.render(source, "script.wg"),
+        "error[E0205]: Division by zero.\n \
+         --> script.wg:2:8\n  \
+         |\n\
+         1 | one #Number is 1\n  \
+         | --- declared here\n\
+         2 | write (one / 0)\n  \
+         |        ^^^^^^^ divisor is zero\n  \
+         |\n  \
+         = note: numbers are never implicitly infinite\n  \
+         = help: use a non-zero divisor: `1`\n"
+    );
+}
+
+#[test]
+fn similar_names_allow_a_typo_per_three_characters() {
+    let names = ["total", "count", "to"];
+    assert_eq!(similar_name("totl", names), Some("total"));
+    assert_eq!(similar_name("cont", names), Some("count"));
+    assert_eq!(similar_name("tx", names), Some("to"));
+    assert_eq!(similar_name("width", names), None);
+}
+
+#[test]
+fn display_includes_severity_and_code() {
+    let diagnostic = Diagnostic::error(ErrorCode::ExpectedExpression, "Expected expression", Span::default());
+    assert_eq!(diagnostic.to_string(), "error[E0101]: Expected expression");
+}
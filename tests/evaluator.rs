@@ -0,0 +1,977 @@
+use wittgenlang::diagnostic::{Diagnostic, ErrorCode};
+use wittgenlang::lexer::Span;
+use wittgenlang::Wittgenlang;
+
+fn eval_error(source: &str) -> Diagnostic {
+    let mut diagnostics = Wittgenlang::new().evaluate(source).unwrap_err();
+    assert_eq!(diagnostics.len(), 1, "expected a single error: {:?}", diagnostics);
+    diagnostics.remove(0)
+}
+
+#[test]
+fn parse_errors_point_at_their_location() {
+    let error = eval_error("one #Number is 1\ntwo #Number is (one + 1");
+    assert_eq!(error.code, ErrorCode::UnexpectedToken);
+    assert_eq!(error.message, "Expected ')' after expression");
+    assert_eq!(error.span(), Span::new(40, 40, 2, 24));
+}
+
+#[test]
+fn runtime_errors_point_at_their_location() {
+    let error = eval_error("one #Number is 1\nwrite (one / 0)");
+    assert_eq!(error.code, ErrorCode::DivisionByZero);
+    assert_eq!(error.span(), Span::new(24, 31, 2, 8));
+}
+
+fn eval(source: &str) -> String {
+    match Wittgenlang::new().evaluate(source) {
+        Ok(result) => result,
+        Err(diagnostics) => panic!("evaluation failed: {:?}", diagnostics),
+    }
+}
+
+#[test]
+fn functions_see_globals_and_other_functions() {
+    let source = "
+offset #Number is 10
+double #Number by {
+  @x #Number
+
+  x * 2
+}
+shifted-double #Number by {
+  @x #Number
+
+  double (x) + offset
+}
+shifted-double (4)";
+    assert_eq!(eval(source), "Number(18.0)");
+}
+
+#[test]
+fn recursive_functions_resolve_themselves() {
+    let source = "
+factorial #Number by {
+  @n #Number
+
+  if n < 2 {
+    1
+  } else {
+    n * factorial (n - 1)
+  }
+}
+factorial (5)";
+    assert_eq!(eval(source), "Number(120.0)");
+}
+
+#[test]
+fn functions_capture_their_declaring_scope() {
+    let source = "
+make-adder #Any by {
+  @amount #Number
+
+  add-amount #Number by {
+    @x #Number
+
+    x + amount
+  }
+  add-amount
+}
+add-three #Any is make-adder (3)
+add-three (4)";
+    assert_eq!(eval(source), "Number(7.0)");
+}
+
+#[test]
+fn block_declarations_do_not_leak() {
+    let error = eval_error("if yes {\n  inner #Number is 1\n}\ninner");
+    assert_eq!(error.code, ErrorCode::UndefinedVariable);
+}
+
+#[test]
+fn misspelled_names_suggest_the_name_in_scope() {
+    let error = eval_error("total #Number is 1
+totl + 1");
+    let suggestion = error.suggestion.expect("a suggestion");
+    assert_eq!(suggestion.replacement, "total");
+    assert_eq!(suggestion.span, Span::new(19, 23, 2, 1));
+
+    let error = eval_error("double #Integer by {\n  @n #Integer\n  n * 2\n}\ndoubel(2)");
+    assert_eq!(error.suggestion.map(|suggestion| suggestion.replacement), Some("double".to_string()));
+    assert!(eval_error("total #Number is 1\nquotient").suggestion.is_none());
+}
+
+#[test]
+fn modules_have_their_own_scope() {
+    let source = "
+scale #Number is 2
+module Math {
+  pi #Number is 3
+  tau #Number is pi * scale
+}
+Math'tau";
+    assert_eq!(eval(source), "Number(6.0)");
+    assert_eq!(eval_error("module Math {\n  pi #Number is 3\n}\npi").code, ErrorCode::UndefinedVariable);
+}
+
+#[test]
+fn produce_exits_the_function_early() {
+    let source = "
+safe-divide #Number by {
+  @numerator #Number
+  @denominator #Number
+
+  if denominator == 0 {
+    produce 0
+  }
+
+  numerator / denominator
+}
+safe-divide (1, 0) + safe-divide (9, 3)";
+    assert_eq!(eval(source), "Number(3.0)");
+}
+
+#[test]
+fn break_and_continue_unwind_to_the_enclosing_loop() {
+    let source = "
+forNow i #Number is 0
+forNow total #Number is 0
+while i < 10 {
+  change i to i + 1
+  if i == 3 {
+    continue
+  }
+  if i > 5 {
+    break
+  }
+  change total to total + i
+}
+total";
+    assert_eq!(eval(source), "Number(12.0)");
+}
+
+#[test]
+fn loop_control_outside_a_loop_is_an_error() {
+    assert_eq!(eval_error("if yes {\n  break\n}").code, ErrorCode::BreakOutsideLoop);
+    assert_eq!(eval_error("continue").code, ErrorCode::ContinueOutsideLoop);
+    assert_eq!(eval_error("produce 1").code, ErrorCode::ProduceOutsideFunction);
+
+    let escapes_function = "
+stop #Bliss by {
+  break
+}
+while yes {
+  stop.
+}";
+    assert_eq!(eval_error(escapes_function).code, ErrorCode::BreakOutsideLoop);
+}
+
+#[test]
+fn for_loops_over_lists_and_ranges() {
+    let source = "
+forNow total #Number is 0
+for n in [1, 2, 3] {
+  change total to total + n
+}
+for i in 1..5 {
+  change total to total + i
+}
+total";
+    // The range visits 1, 2, 3 and 4 but not 5
+    assert_eq!(eval(source), "Number(16.0)");
+
+    assert_eq!(eval("forNow count #Number is 0\nfor i in 3..3 {\n  change count to count + 1\n}\ncount"), "Number(0.0)");
+}
+
+#[test]
+fn for_loops_over_maps_and_text() {
+    let source = "
+forNow keys #Text is \"\"
+forNow total #Integer is 0
+for entry in { \"a\": 1, \"b\": 2 } {
+  (key, value) is entry
+  change keys to keys & key
+  change total to total + value
+}
+(keys, total)";
+    assert_eq!(eval(source), "Tuple([String(\"ab\"), Integer(3)])");
+
+    let source = "
+forNow count #Number is 0
+forNow last #Text is \"\"
+for letter in \"héllo\" {
+  change count to count + 1
+  change last to letter
+}
+last";
+    assert_eq!(eval(source), "String(\"o\")");
+}
+
+#[test]
+fn for_loop_variable_is_fresh_each_iteration() {
+    let source = "
+forNow total #Number is 0
+for i in [1, 2, 3] {
+  if i == 2 {
+    continue
+  }
+  change total to total + i
+}
+total";
+    assert_eq!(eval(source), "Number(4.0)");
+    assert_eq!(eval_error("for i in [1] {\n  write (i)\n}\ni").code, ErrorCode::UndefinedVariable);
+}
+
+#[test]
+fn iterating_a_number_is_an_error() {
+    let error = eval_error("for i in 5 {\n  write (i)\n}");
+    assert_eq!(error.code, ErrorCode::NotIterable);
+    assert_eq!(error.span().line, 1);
+    assert_eq!(eval_error("for i in 1..2.5 {\n}").code, ErrorCode::InvalidOperand);
+}
+
+#[test]
+fn of_runs_the_first_matching_arm() {
+    let source = "
+forNow kind #Text is \"\"
+day #Text is \"Sunday\"
+of day {
+  \"Monday\" -> change kind to \"start\"
+  \"Saturday\", \"Sunday\" -> {
+    change kind to \"weekend\"
+  }
+  \"Sunday\" -> change kind to \"unreachable\"
+  otherwise -> change kind to \"weekday\"
+}
+kind";
+    assert_eq!(eval(source), "String(\"weekend\")");
+}
+
+#[test]
+fn of_falls_back_to_otherwise() {
+    let source = "
+of 3 {
+  1 -> \"one\"
+  2 -> \"two\"
+  otherwise -> \"many\"
+}";
+    assert_eq!(eval(source), "String(\"many\")");
+    assert_eq!(eval("of 3 {\n  1 -> \"one\"\n}"), "Nil");
+}
+
+#[test]
+fn of_yields_the_arm_value_as_an_expression() {
+    let source = "
+describe #Text by {
+  @n #Number
+  produce of n {
+    0 -> \"none\"
+    1 -> \"one\"
+    otherwise -> \"several\"
+  }
+}
+label #Text is of 1 + 1 {
+  2 -> describe(1)
+  otherwise -> \"?\"
+}
+label";
+    assert_eq!(eval(source), "String(\"one\")");
+}
+
+#[test]
+fn lists_and_maps_can_be_indexed() {
+    assert_eq!(eval("numbers #List is [1, 2, 3]\nnumbers[0] + numbers [2]"), "Integer(4)");
+    assert_eq!(eval("ages #Map is { \"alice\": 30, \"bob\": 25 }\nages[\"bob\"]"), "Integer(25)");
+    assert_eq!(eval("grid #List is [[1, 2], [3, 4]]\ngrid[1][0]"), "Integer(3)");
+    assert_eq!(eval("[1, 2, 3, 4][1..3]"), "List([Integer(2), Integer(3)])");
+    assert_eq!(eval("\"hello\"[1]"), "String(\"e\")");
+}
+
+#[test]
+fn bad_indices_are_errors() {
+    let error = eval_error("numbers #List is [1, 2, 3]\nnumbers[3]");
+    assert_eq!(error.code, ErrorCode::IndexOutOfBounds);
+    assert_eq!((error.span().line, error.span().column), (2, 9));
+
+    assert_eq!(eval_error("[1, 2][-1]").code, ErrorCode::IndexOutOfBounds);
+    assert_eq!(eval_error("[1, 2][0..3]").code, ErrorCode::IndexOutOfBounds);
+    assert_eq!(eval_error("{ \"a\": 1 }[\"b\"]").code, ErrorCode::KeyNotFound);
+    assert_eq!(eval_error("5[0]").code, ErrorCode::InvalidOperand);
+}
+
+#[test]
+fn change_updates_elements_in_place() {
+    let source = "
+forNow numbers #List is [1, 2, 3]
+change numbers[0] to 10
+numbers";
+    assert_eq!(eval(source), "List([Integer(10), Integer(2), Integer(3)])");
+
+    let source = "
+forNow ages #Map is { \"alice\": 30 }
+change ages[\"dave\"] to 40
+change ages[\"alice\"] to 31
+ages";
+    assert_eq!(
+        eval(source),
+        "Map([(String(\"alice\"), Integer(31)), (String(\"dave\"), Integer(40))])"
+    );
+
+    let source = "
+forNow grid #List is [[1, 2], [3, 4]]
+change grid[1][0] to 9
+grid[1]";
+    assert_eq!(eval(source), "List([Integer(9), Integer(4)])");
+
+    assert_eq!(
+        eval_error("forNow numbers #List is [1]\nchange numbers[1] to 2").code,
+        ErrorCode::IndexOutOfBounds
+    );
+}
+
+#[test]
+fn only_for_now_values_can_change() {
+    let error = eval_error("one #Number is 1\nchange one to 5");
+    assert_eq!(error.code, ErrorCode::ImmutableAssignment);
+    assert_eq!(error.message, "Cannot change 'one' because it is a constant.");
+    assert_eq!((error.span().line, error.span().column), (2, 1));
+    assert_eq!(eval_error("numbers #List is [1]\nchange numbers[0] to 2").code, ErrorCode::ImmutableAssignment);
+
+    // Inner scopes reach the variable they change
+    assert_eq!(eval("forNow count #Integer is 0\nfor n in 1..4 {\n  change count to count + n\n}\ncount"), "Integer(6)");
+}
+
+#[test]
+fn names_are_declared_once_per_scope() {
+    let error = eval_error("total #Number is 1\ntotal #Number is 2");
+    assert_eq!(error.code, ErrorCode::DuplicateDeclaration);
+    assert_eq!(error.message, "'total' is already declared in this scope.");
+    assert_eq!(eval_error("greet #Text by {\n  \"hi\"\n}\ngreet #Text is \"hello\"").code, ErrorCode::DuplicateDeclaration);
+    let modules = "module Math {\n  one #Number is 1\n}\nmodule Math {\n  two #Number is 2\n}";
+    assert_eq!(eval_error(modules).code, ErrorCode::DuplicateDeclaration);
+
+    // A block may shadow a name from the scope around it
+    assert_eq!(eval("total #Number is 1\nif yes {\n  total #Number is 2\n}\ntotal"), "Number(1.0)");
+}
+
+#[test]
+fn functions_and_types_can_be_used_before_their_declaration() {
+    let source = "
+result #Integer is double(21)
+double #Integer by {
+  @n #Integer
+  n * 2
+}
+result";
+    assert_eq!(eval(source), "Integer(42)");
+    assert_eq!(eval("origin #Point is Point { x: 0 }\nsee #Point is #Record {\n  x #Number\n}\norigin'x"), "Number(0.0)");
+    assert_eq!(eval_error("twice(1)\ndouble #Integer by {\n  @n #Integer\n  n * 2\n}").code, ErrorCode::UndefinedFunction);
+}
+
+#[test]
+fn tuples_hold_a_fixed_number_of_values() {
+    assert_eq!(eval("point #Tuple(Number, Number) is (10.5, 20)\npoint"), "Tuple([Number(10.5), Number(20.0)])");
+    assert_eq!(eval("pair #Tuple(Text, Integer) is (\"age\", 30)\npair[1]"), "Integer(30)");
+    assert_eq!(eval("(1 + 2) * 3"), "Integer(9)");
+    assert_eq!(eval("\"{(1, \"two\")}\""), "String(\"(1, \\\"two\\\")\")");
+    assert_eq!(eval("[(1, 2) = (1, 2), (1, 2) = (2, 1)]"), "List([Boolean(true), Boolean(false)])");
+    assert_eq!(eval_error("(1, 2)[2]").code, ErrorCode::IndexOutOfBounds);
+}
+
+#[test]
+fn destructuring_binds_each_element() {
+    let source = "
+min-max #Tuple(Integer, Integer) by {
+  @numbers #List(Integer)
+  (numbers[0], numbers[2])
+}
+(low, high) is min-max([1, 5, 9])
+high - low";
+    assert_eq!(eval(source), "Integer(8)");
+    assert_eq!(eval("forNow (x, y) is (1, 2)\nchange x to y\nx"), "Integer(2)");
+    assert_eq!(eval_error("(x, y) is (1, 2)\nchange x to y").code, ErrorCode::ImmutableAssignment);
+
+    // Values the checker cannot see into are checked when they are unpacked
+    let error = eval_error("first #Any by {\n  @value #Any\n  (a, b) is value\n  a\n}\nfirst((1, 2, 3))");
+    assert_eq!(error.code, ErrorCode::TypeMismatch);
+    assert_eq!(error.message, "Cannot unpack (1, 2, 3) into 2 names.");
+}
+
+const PERSON: &str = "
+see #Person is #Record {
+  name #Text
+  age #Number
+}
+alice #Person is Person {
+  name: \"Alice\",
+  age: 30
+}
+";
+
+#[test]
+fn records_are_built_from_their_declaration() {
+    assert_eq!(
+        eval(&format!("{PERSON}alice")),
+        "Record { type_name: \"Person\", fields: [(\"name\", String(\"Alice\")), (\"age\", Number(30.0))] }"
+    );
+    // Fields are stored in declaration order whatever order they are given in
+    assert_eq!(
+        eval(&format!("{PERSON}Person {{ age: 1 name: \"Baby\" }}")),
+        "Record { type_name: \"Person\", fields: [(\"name\", String(\"Baby\")), (\"age\", Number(1.0))] }"
+    );
+}
+
+#[test]
+fn record_fields_are_read_with_an_apostrophe() {
+    assert_eq!(eval(&format!("{PERSON}alice'name")), "String(\"Alice\")");
+    assert_eq!(eval_error(&format!("{PERSON}alice'email")).code, ErrorCode::UnknownField);
+}
+
+#[test]
+fn with_produces_an_updated_copy() {
+    let source = format!("{PERSON}bob #Person is alice'with {{ name: \"Bob\" }}\nnames #List is [alice'name, bob'name, bob'age]\nnames");
+    assert_eq!(eval(&source), "List([String(\"Alice\"), String(\"Bob\"), Number(30.0)])");
+    assert_eq!(eval_error(&format!("{PERSON}alice'with {{ email: \"a@b\" }}")).code, ErrorCode::UnknownField);
+}
+
+#[test]
+fn record_construction_validates_fields() {
+    assert_eq!(eval_error(&format!("{PERSON}Person {{ name: \"Eve\" }}")).code, ErrorCode::MissingField);
+    assert_eq!(
+        eval_error(&format!("{PERSON}Person {{ name: \"Eve\", age: 3, email: \"e\" }}")).code,
+        ErrorCode::UnknownField
+    );
+    assert_eq!(
+        eval_error(&format!("{PERSON}Person {{ name: \"Eve\", name: \"Eve\", age: 3 }}")).code,
+        ErrorCode::DuplicateField
+    );
+    let mismatch = eval_error(&format!("{PERSON}Person {{ name: \"Eve\", age: \"old\" }}"));
+    assert_eq!(mismatch.code, ErrorCode::TypeMismatch);
+    assert_eq!(mismatch.span().line, 10);
+    assert_eq!(eval_error("Ghost { name: \"Boo\" }").code, ErrorCode::UndefinedType);
+}
+
+const SHAPE: &str = "
+see #Shape is #Variant {
+  Circle(radius #Number)
+  Rectangle(width #Number, height #Number)
+  Empty
+}
+";
+
+#[test]
+fn variant_constructors_build_tagged_values() {
+    assert_eq!(
+        eval(&format!("{SHAPE}Circle(5)")),
+        "Variant { type_name: \"Shape\", tag: \"Circle\", fields: [(\"radius\", Number(5.0))] }"
+    );
+    assert_eq!(
+        eval(&format!("{SHAPE}Empty")),
+        "Variant { type_name: \"Shape\", tag: \"Empty\", fields: [] }"
+    );
+    assert_eq!(eval(&format!("{SHAPE}Rectangle(2, 3)'height")), "Number(3.0)");
+
+    assert_eq!(eval_error(&format!("{SHAPE}Rectangle(2)")).code, ErrorCode::MissingArgument);
+    assert_eq!(eval_error(&format!("{SHAPE}Circle(1, 2)")).code, ErrorCode::TooManyArguments);
+    assert_eq!(eval_error(&format!("{SHAPE}Circle(\"big\")")).code, ErrorCode::TypeMismatch);
+}
+
+#[test]
+fn if_binds_the_payload_of_a_matching_variant() {
+    let source = format!("{SHAPE}
+shape #Shape is Rectangle(2, 3)
+forNow area #Number is 0
+if shape = Circle(radius) {{
+  change area to radius * radius * 3
+}} else if shape = Rectangle(width, height) {{
+  change area to width * height
+}}
+area");
+    assert_eq!(eval(&source), "Number(6.0)");
+
+    let source = format!("{SHAPE}
+shape #Shape is Empty
+checks #List is [shape = Empty, shape = Circle(r), Circle(2) = Circle(2)]
+checks");
+    assert_eq!(eval(&source), "List([Boolean(true), Boolean(false), Boolean(true)])");
+
+    let source = format!("{SHAPE}
+if Circle(1) = Circle(r) {{
+  r
+}}
+r");
+    assert_eq!(eval_error(&source).code, ErrorCode::UndefinedVariable);
+}
+
+#[test]
+fn of_arms_match_variants_and_bind_fields() {
+    let source = format!("{SHAPE}
+area #Number by {{
+  @shape #Shape
+  of shape {{
+    Circle(0) -> 0
+    Circle(r) -> r * r * 3
+    Rectangle(w, h) -> w * h
+    Empty -> 0
+  }}
+}}
+[area(Circle(2)), area(Rectangle(4, 5)), area(Empty), area(Circle(0))]");
+    assert_eq!(
+        eval(&source),
+        "List([Number(12.0), Number(20.0), Number(0.0), Number(0.0)])"
+    );
+
+    let source = format!("{SHAPE}of Circle(1) {{\n  Circle(a, b) -> a\n}}");
+    assert_eq!(eval_error(&source).code, ErrorCode::InvalidPattern);
+}
+
+#[test]
+fn generic_types_hold_any_element_type() {
+    let source = "
+see #BinaryTree(T) is #Variant {
+  Leaf
+  Node(value #T, left #BinaryTree(T), right #BinaryTree(T))
+}
+see #Pair(A, B) is #Record {
+  first #A
+  second #B
+}
+total #Integer by {
+  @tree #BinaryTree(Integer)
+  of tree {
+    Leaf -> 0
+    Node(value, left, right) -> value + total(left) + total(right)
+  }
+}
+tree #BinaryTree(Integer) is Node(1, Node(2, Leaf, Leaf), Node(3, Leaf, Leaf))
+pair #Pair(Text, BinaryTree(Integer)) is Pair { first: \"sum\", second: tree }
+[pair'first, total(pair'second)]";
+    assert_eq!(eval(source), "List([String(\"sum\"), Integer(6)])");
+}
+
+#[test]
+fn result_and_optional_need_no_declaration() {
+    let source = "
+divide #Result by {
+  @numerator #Number
+  @denominator #Number
+  if denominator == 0 {
+    produce Error(\"Division by zero\")
+  }
+  Success(numerator / denominator)
+}
+describe #Text by {
+  @result #Result
+  of result {
+    Success(value) -> \"ok\"
+    Error(message) -> message
+  }
+}
+outcomes #List is [describe(divide(6, 3)), describe(divide(1, 0))]
+outcomes";
+    assert_eq!(eval(source), "List([String(\"ok\"), String(\"Division by zero\")])");
+
+    let source = "
+maybe-number #Number* is Some(42)
+maybe-text #Text* is None
+forNow total #Number is 0
+if maybe-number = Some(value) {
+  change total to value
+}
+if maybe-text = Some(value) {
+  change total to 0
+}
+total";
+    assert_eq!(eval(source), "Number(42.0)");
+}
+
+#[test]
+fn prelude_names_can_be_shadowed() {
+    let source = "
+see #Outcome is #Variant {
+  Success(count #Number)
+}
+Success(3)'count";
+    assert_eq!(eval(source), "Number(3.0)");
+}
+
+const DIVIDE: &str = "
+divide #Result by {
+  @numerator #Number
+  @denominator #Number
+  if denominator == 0 {
+    produce Error(\"Division by zero\")
+  }
+  Success(numerator / denominator)
+}
+";
+
+#[test]
+fn try_unwraps_success_and_returns_errors_early() {
+    let source = format!("{DIVIDE}
+ratio #Result by {{
+  @a #Number
+  @b #Number
+  @c #Number
+  first #Number is try divide(a, b)
+  second #Number is try divide(first, c)
+  Success(second)
+}}
+results #List is [ratio(12, 2, 3), ratio(1, 0, 3)]
+results");
+    assert_eq!(
+        eval(&source),
+        "List([Variant { type_name: \"Result\", tag: \"Success\", fields: [(\"value\", Number(2.0))] }, \
+Variant { type_name: \"Result\", tag: \"Error\", fields: [(\"message\", String(\"Division by zero\"))] }])"
+    );
+}
+
+#[test]
+fn try_maps_errors_through_the_handler() {
+    let source = format!("{DIVIDE}
+checked #Result by {{
+  @x #Number
+  try divide(x, 0) | (error) -> Error([\"wrapped\", error])
+}}
+checked(1)");
+    assert_eq!(
+        eval(&source),
+        "Variant { type_name: \"Result\", tag: \"Error\", fields: [(\"message\", \
+List([String(\"wrapped\"), String(\"Division by zero\")]))] }"
+    );
+}
+
+#[test]
+fn try_misuse_is_reported() {
+    let error = eval_error(&format!("{DIVIDE}try divide(1, 0)"));
+    assert_eq!(error.code, ErrorCode::UnhandledError);
+    assert_eq!(error.span().line, 10);
+
+    assert_eq!(eval(&format!("{DIVIDE}try divide(4, 2)")), "Number(2.0)");
+    assert_eq!(eval_error("try 5").code, ErrorCode::TypeMismatch);
+}
+
+#[test]
+fn rescue_catches_runtime_errors_and_continues() {
+    let source = "
+forNow value #Number is 1
+forNow caught #Text is \"\"
+rescue {
+  change value to 10 / 0
+  change value to 2
+} catch error {
+  change caught to error'message
+  change value to 0
+}
+outcome #List is [value, caught]
+outcome";
+    assert_eq!(eval(source), "List([Number(0.0), String(\"Division by zero.\")])");
+    assert_eq!(
+        eval_error("rescue {\n  1 / 0\n} catch error {\n}\nerror").code,
+        ErrorCode::UndefinedVariable
+    );
+
+    let source = "
+forNow details #List is []
+rescue {
+  missing + 1
+} catch error {
+  change details to [error'kind, error'code, error'line, error'column]
+}
+details";
+    assert_eq!(
+        eval(source),
+        "List([String(\"UndefinedVariable\"), String(\"E0200\"), Integer(4), Integer(3)])"
+    );
+
+    let source = "
+forNow value #Number is 1
+rescue {
+  change value to 10 / 0
+} catch error {
+  change value to 0
+}
+value + 5";
+    assert_eq!(eval(source), "Number(5.0)");
+}
+
+#[test]
+fn rescue_lets_control_flow_through() {
+    let source = "
+first-even #Number by {
+  @numbers #List
+  for n in numbers {
+    rescue {
+      if n == 2 {
+        produce n
+      }
+    } catch error {
+      produce 0
+    }
+  }
+  -1
+}
+first-even([1, 2, 3])";
+    assert_eq!(eval(source), "Number(2.0)");
+
+    let source = "
+forNow count #Number is 0
+while yes {
+  rescue {
+    break
+  } catch error {
+    change count to 99
+  }
+}
+count";
+    assert_eq!(eval(source), "Number(0.0)");
+}
+
+#[test]
+fn lambdas_are_first_class_values() {
+    assert_eq!(eval("double #Any is (x) -> x * 2\ndouble (5)"), "Integer(10)");
+    assert_eq!(eval("add #Any is (x #Number, y #Number) -> x + y\nadd (3, 4)"), "Number(7.0)");
+    assert_eq!(eval("((x, y) -> x * y) (6, 7)"), "Integer(42)");
+    assert_eq!(eval("constant #Any is () -> 1\nconstant()"), "Integer(1)");
+
+    let source = "
+apply #Any by {
+  @f #Any
+  @value #Number
+  f(value)
+}
+apply((n) -> n + 1, 41)";
+    assert_eq!(eval(source), "Number(42.0)");
+}
+
+#[test]
+fn block_lambdas_can_produce_early() {
+    let source = "
+validate #Any is (value) -> {
+  if value < 0 {
+    produce no
+  }
+  if value > 100 {
+    produce no
+  }
+  yes
+}
+results #List is [validate(42), validate(150), validate(-1)]
+results";
+    assert_eq!(eval(source), "List([Boolean(true), Boolean(false), Boolean(false)])");
+}
+
+#[test]
+fn lambdas_capture_their_scope() {
+    let source = "
+make-adder #Any by {
+  @amount #Number
+  (n) -> n + amount
+}
+add-ten #Any is make-adder(10)
+factor #Number is 3
+scale #Any is (n) -> n * factor
+[add-ten(1), scale(2)]";
+    assert_eq!(eval(source), "List([Number(11.0), Number(6.0)])");
+}
+
+#[test]
+fn calling_a_non_function_is_an_error() {
+    assert_eq!(eval_error("five #Number is 5\nfive(1)").code, ErrorCode::InvalidOperand);
+    assert_eq!(eval_error("missing(1)").code, ErrorCode::UndefinedFunction);
+}
+
+const SUBTRACT: &str = "
+subtract #Number by {
+  @total #Number
+  @amount #Number
+  total - amount
+}
+";
+
+#[test]
+fn named_arguments_bind_by_name() {
+    assert_eq!(eval(&format!("{SUBTRACT}subtract (amount: 1, total: 10)")), "Number(9.0)");
+    assert_eq!(eval(&format!("{SUBTRACT}subtract (10, amount: 4)")), "Number(6.0)");
+    assert_eq!(
+        eval(&format!("{SHAPE}Rectangle(height: 2, width: 5)'width")),
+        "Number(5.0)"
+    );
+}
+
+#[test]
+fn argument_mismatches_are_reported() {
+    let too_many = eval_error(&format!("{SUBTRACT}subtract (1, 2, 3)"));
+    assert_eq!(too_many.code, ErrorCode::TooManyArguments);
+    assert_eq!((too_many.span().line, too_many.span().column), (7, 17));
+
+    assert_eq!(eval_error(&format!("{SUBTRACT}subtract (1)")).code, ErrorCode::MissingArgument);
+    let missing = eval_error(&format!("{SUBTRACT}apply #Any by {{\n  @f #Any\n  f(1)\n}}\napply(subtract)"));
+    assert_eq!(missing.message, "Missing argument for parameter 'amount' of 'subtract'.");
+    assert_eq!(
+        eval_error(&format!("{SUBTRACT}subtract (1, step: 2)")).code,
+        ErrorCode::UnknownArgument
+    );
+    assert_eq!(
+        eval_error(&format!("{SUBTRACT}subtract (1, total: 2)")).code,
+        ErrorCode::DuplicateArgument
+    );
+    assert_eq!(
+        eval_error("((x) -> x) (1, 2)").code,
+        ErrorCode::TooManyArguments
+    );
+}
+
+#[test]
+fn word_operators_combine_decisions() {
+    assert_eq!(eval("yes and not no"), "Boolean(true)");
+    assert_eq!(eval("no or 1 = 1"), "Boolean(true)");
+    assert_eq!(eval("1 != 2"), "Boolean(true)");
+    // The right side never runs, so the undefined name is not an error
+    assert_eq!(eval("no and missing"), "Boolean(false)");
+    assert_eq!(eval("yes or missing"), "Boolean(true)");
+}
+
+#[test]
+fn conditions_joined_with_and_bind_each_pattern() {
+    let source = "first #Optional is Some(2)
+second #Optional is Some(3)
+forNow total #Number is 0
+if first = Some(a) and second = Some(b) {
+  change total to a + b
+}
+total";
+    assert_eq!(eval(source), "Number(5.0)");
+}
+
+#[test]
+fn strings_interpolate_expressions() {
+    assert_eq!(eval("answer #Number is 42\n\"The answer is {answer}.\""), "String(\"The answer is 42.\")");
+    assert_eq!(eval("\"{1 + 1} and {\"nested {yes}\"}\""), "String(\"2 and nested yes\")");
+    assert_eq!(eval("\"tab\\tquote\\\" brace\\{\""), "String(\"tab\\tquote\\\" brace{\")");
+    assert_eq!(eval(&format!("{PERSON}\"{{alice'name}} is {{alice'age}}\"")), "String(\"Alice is 30\")");
+}
+
+#[test]
+fn values_display_as_they_are_written() {
+    assert_eq!(eval("\"{[1, 2.5, \"a\"]}\""), "String(\"[1, 2.5, \\\"a\\\"]\")");
+    assert_eq!(eval("\"{{ \"one\": 1 }} {1..3} {nothing}\""), "String(\"{ \\\"one\\\": 1 } 1..3 nothing\")");
+    assert_eq!(eval(&format!("{PERSON}\"{{alice}}\"")), "String(\"Person { name: \\\"Alice\\\", age: 30 }\")");
+    assert_eq!(eval("\"{Some(3)} {None} {no}\""), "String(\"Some(3) None no\")");
+}
+
+#[test]
+fn text_blocks_interpolate_and_strip_indentation() {
+    let source = "name #Text is \"Ada\"
+greeting #Text is \"\"\"
+  Hello {name},
+    welcome.
+  \"\"\"
+greeting";
+    assert_eq!(eval(source), "String(\"Hello Ada,\\n  welcome.\")");
+}
+
+#[test]
+fn integers_stay_exact() {
+    assert_eq!(eval("9007199254740993 + 2"), "Integer(9007199254740995)");
+    assert_eq!(eval("count #Integer is 7\ncount * 6"), "Integer(42)");
+    assert_eq!(eval("2 ^ 62"), "Integer(4611686018427387904)");
+    assert_eq!(eval("7 / 2"), "Number(3.5)");
+    assert_eq!(eval("7 + 0.5"), "Number(7.5)");
+    assert_eq!(eval("1 = 1.0"), "Boolean(true)");
+    // #Number declarations, parameters and fields widen integers
+    assert_eq!(eval("amount #Number is 42\namount"), "Number(42.0)");
+    assert_eq!(eval(&format!("{SUBTRACT}subtract (10, 4)")), "Number(6.0)");
+
+    let overflow = eval_error("big #Integer is 9223372036854775807\nbig + 1");
+    assert_eq!(overflow.code, ErrorCode::IntegerOverflow);
+    assert_eq!((overflow.span().line, overflow.span().column), (2, 1));
+    assert_eq!(eval_error("2 ^ 63").code, ErrorCode::IntegerOverflow);
+}
+
+#[test]
+fn minus_between_literals_subtracts() {
+    assert_eq!(eval("5-3"), "Integer(2)");
+    assert_eq!(eval("x #Number is 5-3\nx"), "Number(2.0)");
+    assert_eq!(eval("[-1, 2.5-0.5, - 2]"), "List([Integer(-1), Number(2.0), Integer(-2)])");
+    assert_eq!(eval("-9223372036854775808"), "Integer(-9223372036854775808)");
+    assert_eq!(eval_error("9223372036854775808").code, ErrorCode::IntegerOverflow);
+}
+
+#[test]
+fn integer_division_floors_and_remainder_follows_the_divisor() {
+    assert_eq!(eval("[7 // 2, -7 // 2, 7 // -2]"), "List([Integer(3), Integer(-4), Integer(-4)])");
+    assert_eq!(eval("[7 % 3, -7 % 3, 7 % -3]"), "List([Integer(1), Integer(2), Integer(-2)])");
+    assert_eq!(eval("2 ^ -1"), "Number(0.5)");
+    assert_eq!(eval_error("1 // 0").code, ErrorCode::DivisionByZero);
+    assert_eq!(eval_error("1 % 0").code, ErrorCode::DivisionByZero);
+}
+
+#[test]
+fn declared_numbers_widen_returns_and_elements() {
+    let source = "f #Number by {\n  9223372036854775807\n}\nf() + 1";
+    assert_eq!(eval(source), "Number(9.223372036854776e18)");
+    assert_eq!(eval("safe #Number by {\n  produce 0\n}\nsafe()"), "Number(0.0)");
+
+    assert_eq!(eval("xs #List(Number) is [9223372036854775807]\nxs[0] + 1"), "Number(9.223372036854776e18)");
+    assert_eq!(eval("ages #Map(Text, Number) is {\"ann\": 3}\nages"), "Map([(String(\"ann\"), Number(3.0))])");
+    assert_eq!(eval("counts #List(Integer) is [1, 2]\ncounts"), "List([Integer(1), Integer(2)])");
+}
+
+#[test]
+fn changes_conform_to_the_declared_type() {
+    assert_eq!(eval("forNow xs #List(Number) is [1.5]\nchange xs to [1, 2]\nxs"), "List([Number(1.0), Number(2.0)])");
+    assert_eq!(eval("forNow xs #List(Number) is [1.5]\nchange xs[0] to 3\nxs"), "List([Number(3.0)])");
+    assert_eq!(
+        eval("forNow ages #Map(Text, Number) is {}\nchange ages to {\"ann\": 3}\nages"),
+        "Map([(String(\"ann\"), Number(3.0))])"
+    );
+    assert_eq!(
+        eval("forNow point #Tuple(Number, Text) is (0.5, \"a\")\nchange point to (1, \"b\")\npoint"),
+        "Tuple([Number(1.0), String(\"b\")])"
+    );
+    let source = "
+see #Box is #Record {
+  sizes #List(Number)
+}
+forNow box #Box is Box { sizes: [0.5] }
+change box to Box { sizes: [1] }
+box'sizes";
+    assert_eq!(eval(source), "List([Number(1.0)])");
+    assert_eq!(eval("forNow count #Number is 0\nchange count to 1\ncount"), "Number(1.0)");
+}
+
+#[test]
+fn integers_convert_to_and_from_numbers() {
+    assert_eq!(eval("count #Integer is 3\ncount'to-number"), "Number(3.0)");
+    assert_eq!(eval("price #Number is -2.75\nprice'to-integer"), "Integer(-2)");
+    assert_eq!(eval_error("huge #Number is 9999999999.0 * 9999999999.0\nhuge'to-integer").code, ErrorCode::IntegerOverflow);
+}
+
+#[test]
+fn number_division_remainder_and_powers() {
+    assert_eq!(eval("[7.5 // 2.0, -7.5 // 2.0]"), "List([Number(3.0), Number(-4.0)])");
+    assert_eq!(eval("[7.5 % 2.0, -7.5 % 2.0, 7.5 % -2.0]"), "List([Number(1.5), Number(0.5), Number(-0.5)])");
+    assert_eq!(eval("2.0 ^ 3"), "Number(8.0)");
+    assert_eq!(eval("[5 % 2.5, 9 ^ 0.5]"), "List([Number(0.0), Number(3.0)])");
+    assert_eq!(eval_error("1.5 % 0.0").code, ErrorCode::DivisionByZero);
+    assert_eq!(eval_error("\"a\" ^ 2").code, ErrorCode::InvalidOperand);
+}
+
+#[test]
+fn ampersand_joins_values_as_text() {
+    assert_eq!(eval("\"Total: \" & 42 & \"!\""), "String(\"Total: 42!\")");
+    assert_eq!(eval("[1, 2] & \" and \" & yes"), "String(\"[1, 2] and yes\")");
+}
+
+#[test]
+fn equality_compares_structure() {
+    assert_eq!(eval("[1, [2, \"x\"]] = [1, [2, \"x\"]]"), "Boolean(true)");
+    assert_eq!(eval("[1, 2] = [1, 2, 3]"), "Boolean(false)");
+    assert_eq!(eval("{ \"a\": 1, \"b\": 2 } = { \"b\": 2, \"a\": 1 }"), "Boolean(true)");
+    assert_eq!(eval("{ \"a\": 1 } != { \"a\": 2 }"), "Boolean(true)");
+    assert_eq!(eval(&format!("{PERSON}alice = Person {{ name: \"Alice\", age: 30 }}")), "Boolean(true)");
+    assert_eq!(eval(&format!("{PERSON}alice = alice'with {{ age: 31 }}")), "Boolean(false)");
+    assert_eq!(eval("Some([1, 2]) = Some([1, 2])"), "Boolean(true)");
+    assert_eq!(eval("(1..3) = (1..3)"), "Boolean(true)");
+}
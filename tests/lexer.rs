@@ -0,0 +1,100 @@
+use wittgenlang::lexer::{Lexer, Span, StringPart, SpannedToken, Token};
+
+#[test]
+fn tokens_carry_byte_offsets_lines_and_columns() {
+    let tokens: Vec<_> = Lexer::new("one #Number is 1\n  write (one)").collect();
+
+    assert_eq!(tokens[0].token, Token::Identifier("one".to_string()));
+    assert_eq!(tokens[0].span, Span::new(0, 3, 1, 1));
+    assert_eq!(tokens[1].span, Span::new(4, 11, 1, 5));
+    assert_eq!(tokens[4].token, Token::Write);
+    assert_eq!(tokens[4].span, Span::new(19, 24, 2, 3));
+}
+
+#[test]
+fn lexer_ends_with_eof_at_end_of_input() {
+    let tokens: Vec<_> = Lexer::new("yes\n").collect();
+    let last = tokens.last().unwrap();
+
+    assert_eq!(last.token, Token::EOF);
+    assert_eq!(last.span, Span::new(4, 4, 2, 1));
+}
+
+#[test]
+fn invalid_input_becomes_an_error_token() {
+    let tokens: Vec<_> = Lexer::new("a $ b").map(|t| t.token).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Identifier("a".to_string()),
+            Token::Error("$".to_string()),
+            Token::Identifier("b".to_string()),
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn range_between_numbers_is_not_a_decimal_point() {
+    let tokens: Vec<_> = Lexer::new("1..5").map(|t| t.token).collect();
+
+    assert_eq!(tokens, vec![Token::Integer(1), Token::Range, Token::Integer(5), Token::EOF]);
+}
+
+#[test]
+fn minus_is_always_an_operator() {
+    let tokens: Vec<_> = Lexer::new("5-3").map(|t| t.token).collect();
+
+    assert_eq!(tokens, vec![Token::Integer(5), Token::Minus, Token::Integer(3), Token::EOF]);
+}
+
+#[test]
+fn comments_skip_to_end_of_line_but_not_inequality() {
+    let tokens: Vec<_> = Lexer::new("1 != 2 ! trailing note\n@x").map(|t| t.token).collect();
+
+    assert_eq!(
+        tokens,
+        vec![
+            Token::Integer(1),
+            Token::NotEqual,
+            Token::Integer(2),
+            Token::At,
+            Token::Identifier("x".to_string()),
+            Token::EOF,
+        ]
+    );
+}
+
+#[test]
+fn doc_comment_blocks_span_lines() {
+    let tokens: Vec<_> = Lexer::new("!doc \"\"\"\n  Adds things.\n\"\"\"\nyes").map(|t| t.token).collect();
+
+    assert_eq!(tokens, vec![Token::Yes, Token::EOF]);
+}
+
+#[test]
+fn strings_split_into_text_and_embedded_code() {
+    let tokens: Vec<_> = Lexer::new("x is \"a\\\"b {n + 1}\\{\"").map(|t| t.token).collect();
+
+    assert_eq!(
+        tokens[2],
+        Token::String(vec![
+            StringPart::Text("a\"b ".to_string()),
+            StringPart::Code(vec![
+                SpannedToken { token: Token::Identifier("n".to_string()), span: Span::new(12, 13, 1, 13) },
+                SpannedToken { token: Token::Plus, span: Span::new(14, 15, 1, 15) },
+                SpannedToken { token: Token::Integer(1), span: Span::new(16, 17, 1, 17) },
+                SpannedToken { token: Token::EOF, span: Span::new(17, 17, 1, 18) },
+            ]),
+            StringPart::Text("{".to_string()),
+        ])
+    );
+}
+
+#[test]
+fn text_blocks_drop_common_indentation() {
+    let tokens: Vec<_> = Lexer::new("x is \"\"\"\n    one\n      two\n    \"\"\"").map(|t| t.token).collect();
+
+    assert_eq!(tokens[2], Token::String(vec![StringPart::Text("one\n  two".to_string())]));
+}
@@ -0,0 +1,217 @@
+use wittgenlang::diagnostic::ErrorCode;
+use wittgenlang::parser::{Expr, ExprKind, Parser, StmtKind, Type, TypeDefinition};
+
+#[test]
+fn reports_every_syntax_error_in_one_pass() {
+    let source = "\
+one #Number is (1 + )
+two #Number is 2
+if two > 1 {
+  write (two
+  write (one)
+}
+three #Number is )
+";
+    let (statements, diagnostics) = Parser::new(source).parse();
+
+    let lines: Vec<_> = diagnostics.iter().map(|d| d.span().line).collect();
+    assert_eq!(lines, vec![1, 5, 7]);
+    assert_eq!(diagnostics[0].code, ErrorCode::ExpectedExpression);
+    assert_eq!(diagnostics[1].code, ErrorCode::UnexpectedToken);
+
+    // `two` and the `if` survive; the broken declarations are dropped.
+    assert_eq!(statements.len(), 2);
+    assert!(matches!(&statements[0].kind, StmtKind::Value { name, .. } if name == "two"));
+    match &statements[1].kind {
+        StmtKind::If { then_branch, .. } => assert_eq!(then_branch.len(), 1),
+        other => panic!("expected if statement, got {:?}", other),
+    }
+}
+
+#[test]
+fn stray_closing_brace_does_not_swallow_the_rest_of_the_file() {
+    let (statements, diagnostics) = Parser::new("}\nx #Number is 1").parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(statements.len(), 1);
+}
+
+#[test]
+fn invalid_characters_are_reported_without_truncating_the_program() {
+    let (statements, diagnostics) = Parser::new("x #Number is 1 $$\ny #Number is 2").parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::InvalidCharacter);
+    assert_eq!(diagnostics[0].message, "Unexpected character '$$'");
+    assert_eq!(diagnostics[0].span().column, 16);
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn star_suffix_is_optional_sugar() {
+    let source = "\
+maybe #Number* is None
+find #Person* by {
+  @name #Text*
+  None
+}
+see #Contact is #Record {
+  phone #Text*
+}
+";
+    let (statements, diagnostics) = Parser::new(source).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    assert!(matches!(&statements[0].kind, StmtKind::Value { value_type, .. } if value_type.to_string() == "#Optional(Number)"));
+    match &statements[1].kind {
+        StmtKind::Function { return_type, params, .. } => {
+            assert_eq!(return_type.to_string(), "#Optional(Person)");
+            assert_eq!(params[0].1.to_string(), "#Optional(Text)");
+        }
+        other => panic!("expected function, got {:?}", other),
+    }
+    match &statements[2].kind {
+        StmtKind::TypeDefinition { definition: TypeDefinition::Record { fields }, .. } => {
+            assert_eq!(fields[0].1.to_string(), "#Optional(Text)");
+        }
+        other => panic!("expected record definition, got {:?}", other),
+    }
+}
+
+#[test]
+fn parses_compound_type_annotations() {
+    let source = "\
+scores #Map(Text, List(Number)) is {}
+apply #(Number, Text) -> #Decision is (x #Number, y #Text) -> yes
+id #Integer | #Text is 1
+pair #Pair(Number, Text*) is nothing
+see #Age is #Number
+see #Lookup is #Map(Text, Integer)
+";
+    let (statements, diagnostics) = Parser::new(source).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    let declared: Vec<String> = statements[..4]
+        .iter()
+        .map(|stmt| match &stmt.kind {
+            StmtKind::Value { value_type, .. } => value_type.to_string(),
+            other => panic!("expected declaration, got {:?}", other),
+        })
+        .collect();
+    assert_eq!(declared, [
+        "#Map(Text, List(Number))",
+        "#(Number, Text) -> #Decision",
+        "#Integer | #Text",
+        "#Pair(Number, Optional(Text))",
+    ]);
+    assert!(matches!(
+        &statements[4].kind,
+        StmtKind::TypeDefinition { definition: TypeDefinition::Alias(Type::Primitive(name)), .. } if name == "Number"
+    ));
+    assert!(matches!(
+        &statements[5].kind,
+        StmtKind::TypeDefinition { definition: TypeDefinition::Alias(Type::Map(..)), .. }
+    ));
+}
+
+#[test]
+fn a_new_line_ends_a_call_chain() {
+    let (statements, diagnostics) = Parser::new("total #Number is one\n(2)\n[3]").parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert_eq!(statements.len(), 3);
+
+    let (statements, _) = Parser::new("f(1,\n  2)").parse();
+    assert_eq!(statements.len(), 1);
+}
+
+#[test]
+fn bare_by_without_a_function_name_is_an_error() {
+    let (statements, diagnostics) = Parser::new("by { }").parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Expected function name before 'by'");
+    assert!(statements.is_empty());
+}
+
+#[test]
+fn oversized_integer_literals_are_reported_as_such() {
+    let (statements, diagnostics) = Parser::new("x #Integer is 99999999999999999999\ny #Number is 2").parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::IntegerOverflow);
+    assert_eq!(diagnostics[0].message, "Integer literal too large");
+    assert_eq!(diagnostics[0].span().column, 15);
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn errors_inside_interpolation_point_into_the_string() {
+    let (_, diagnostics) = Parser::new("x #Text is \"sum: {1 + }\"").parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::ExpectedExpression);
+    assert_eq!((diagnostics[0].span().line, diagnostics[0].span().column), (1, 23));
+
+    let (statements, diagnostics) = Parser::new("x #Text is \"oops {1}").parse();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::UnterminatedString);
+    assert_eq!(statements.len(), 1);
+
+    let (_, diagnostics) = Parser::new("write (\"never closed").parse();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::UnterminatedString);
+}
+
+#[test]
+fn unclosed_interpolation_points_at_the_brace() {
+    let (statements, diagnostics) = Parser::new("x #Text is \"a { b\"\ny #Number is 2").parse();
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::UnclosedInterpolation);
+    assert_eq!((diagnostics[0].span().line, diagnostics[0].span().column), (1, 15));
+    assert_eq!(statements.len(), 2);
+}
+
+#[test]
+fn type_definitions_take_type_parameters() {
+    let source = "\
+see #Pair(A, B = Text) is #Record {
+  first #A
+  second #B
+}
+see #BinaryTree(T) is #Variant {
+  Leaf
+  Node(value #T, left #BinaryTree(T), right #BinaryTree(T))
+}
+";
+    let (statements, diagnostics) = Parser::new(source).parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    match &statements[0].kind {
+        StmtKind::TypeDefinition { name, parameters, .. } => {
+            assert_eq!(name, "Pair");
+            assert_eq!(parameters, &[
+                ("A".to_string(), None),
+                ("B".to_string(), Some(Type::Primitive("Text".to_string()))),
+            ]);
+        }
+        other => panic!("expected type definition, got {:?}", other),
+    }
+    match &statements[1].kind {
+        StmtKind::TypeDefinition { definition: TypeDefinition::Variant { variants }, .. } => {
+            assert_eq!(variants[1].1[1].1.to_string(), "#BinaryTree(T)");
+        }
+        other => panic!("expected variant definition, got {:?}", other),
+    }
+}
+
+#[test]
+fn parentheses_group_tuple_and_destructure() {
+    let (statements, diagnostics) = Parser::new("(1 + 2)\n(1, 2)\n(low, high) is (1, 2)\nforNow (a, b) is pair").parse();
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    assert!(matches!(&statements[0].kind, StmtKind::Expression(Expr { kind: ExprKind::Grouping(_), .. })));
+    assert!(matches!(&statements[1].kind, StmtKind::Expression(Expr { kind: ExprKind::Tuple(elements), .. }) if elements.len() == 2));
+    assert!(matches!(&statements[2].kind, StmtKind::Destructure { names, mutable: false, .. } if names == &["low", "high"]));
+    assert!(matches!(&statements[3].kind, StmtKind::Destructure { mutable: true, .. }));
+}
@@ -0,0 +1,322 @@
+use wittgenlang::diagnostic::{Diagnostic, ErrorCode};
+use wittgenlang::parser::Parser;
+use wittgenlang::typecheck::{self, TypeChecker};
+
+fn check(source: &str) -> Vec<Diagnostic> {
+    let (statements, diagnostics) = Parser::new(source).parse();
+    assert!(diagnostics.is_empty(), "syntax errors: {:?}", diagnostics);
+    typecheck::check(&statements)
+}
+
+const SHAPE: &str = "
+see #Shape is #Variant {
+  Circle(radius #Number)
+  Rectangle(width #Number, height #Number)
+  Empty
+}
+";
+
+#[test]
+fn declarations_must_match_their_annotation() {
+    let diagnostics = check("name #Text is 42");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::TypeMismatch);
+    assert_eq!(diagnostics[0].message, "Expected #Text for 'name' but found #Integer.");
+    assert_eq!((diagnostics[0].span().line, diagnostics[0].span().column), (1, 15));
+
+    // Integers widen to numbers, and unknown values are left to the evaluator
+    assert!(check("amount #Number is 42\ntotal #Number is amount * 2").is_empty());
+    assert!(check("anything #Text is missing").is_empty());
+    assert!(check("forNow count #Integer is 0\nchange count to \"one\"")[0].message.contains("'count'"));
+}
+
+#[test]
+fn calls_are_checked_against_parameters() {
+    let source = "
+greet #Text by {
+  @name #Text
+  @times #Integer
+  name
+}
+greet(3, times: \"twice\")";
+    let diagnostics = check(source);
+    assert_eq!(diagnostics.len(), 2);
+    assert_eq!(diagnostics[0].message, "Expected #Text for 'name' of 'greet' but found #Integer.");
+    assert_eq!(diagnostics[1].message, "Expected #Integer for 'times' of 'greet' but found #Text.");
+
+    let diagnostics = check(&format!("{SHAPE}Rectangle(2, \"tall\")"));
+    assert_eq!(diagnostics[0].code, ErrorCode::TypeMismatch);
+    assert!(check(&format!("{SHAPE}shape #Shape is Circle(2)")).is_empty());
+    assert_eq!(check(&format!("{SHAPE}shape #Text is Empty")).len(), 1);
+}
+
+#[test]
+fn calls_must_give_each_parameter_once() {
+    let greet = "greet #Text by {\n  @name #Text\n  @times #Integer\n  name\n}\n";
+    let diagnostics = check(&format!("{greet}greet(\"a\", 2, 3)"));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::TooManyArguments);
+    assert_eq!(diagnostics[0].message, "'greet' takes 2 argument(s) but 3 were given.");
+    assert_eq!((diagnostics[0].span().line, diagnostics[0].span().column), (6, 15));
+    assert_eq!(diagnostics[0].labels[0].message, "takes 2 argument(s)");
+    assert_eq!(diagnostics[0].labels[0].span.column, 1);
+
+    let diagnostics = check(&format!("{greet}greet(\"a\", count: 2)"));
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::UnknownArgument);
+    assert_eq!(diagnostics[0].message, "'greet' has no parameter named 'count'.");
+
+    let diagnostics = check(&format!("{greet}greet(\"a\")"));
+    assert_eq!(diagnostics[0].message, "Missing argument for parameter 'times' of 'greet'.");
+    assert_eq!(check(&format!("{SHAPE}Rectangle(2, width: 3)"))[0].code, ErrorCode::DuplicateArgument);
+    assert_eq!(check("twice #Any is (x #Number) -> x * 2\ntwice(1, 2)")[0].code, ErrorCode::TooManyArguments);
+    assert!(check(&format!("{greet}greet(times: 2, name: \"a\")")).is_empty());
+}
+
+#[test]
+fn returns_match_the_declared_type() {
+    let source = "
+label #Text by {
+  @n #Integer
+  if n > 1 {
+    produce n
+  }
+  \"one\"
+}";
+    let diagnostics = check(source);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Expected #Text for the produced value but found #Integer.");
+
+    let diagnostics = check("double #Number by {\n  @n #Number\n  \"twice\"\n}");
+    assert_eq!(diagnostics[0].message, "Expected #Number for the result of 'double' but found #Text.");
+    assert!(check("log #Bliss by {\n  @n #Number\n  n\n}").is_empty());
+}
+
+#[test]
+fn of_must_handle_every_variant_case() {
+    let source = format!("{SHAPE}
+area #Number by {{
+  @shape #Shape
+  of shape {{
+    Circle(r) -> r * r * 3
+    Rectangle(2, h) -> h * 2
+  }}
+}}");
+    let diagnostics = check(&source);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::NonExhaustiveMatch);
+    assert_eq!(diagnostics[0].message, "'of' does not handle Rectangle, Empty of #Shape.");
+
+    let source = format!("{SHAPE}
+shape #Shape is Empty
+of shape {{
+  Circle(r) -> r
+  otherwise -> 0
+}}");
+    assert!(check(&source).is_empty());
+
+    let source = "maybe #Text* is None\nof maybe {\n  Some(text) -> text\n}";
+    assert_eq!(check(source)[0].message, "'of' does not handle None of #Optional.");
+}
+
+#[test]
+fn of_expressions_have_the_type_of_their_arms() {
+    let diagnostics = check("label #Text is of 2 {\n  1 -> 1\n  otherwise -> 2\n}");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Expected #Text for 'label' but found #Integer.");
+    assert!(check("label #Text is of 2 {\n  1 -> \"one\"\n  otherwise -> \"many\"\n}").is_empty());
+    // Arms that disagree could yield either, so only the evaluator can tell
+    assert!(check("label #Text is of 2 {\n  1 -> \"one\"\n  otherwise -> 2\n}").is_empty());
+
+    let source = format!("{SHAPE}
+shape #Shape is Empty
+area #Text is of shape {{
+  Circle(r) -> r * r * 3
+  Rectangle(w, h) -> w * h
+  Empty -> 0
+}}");
+    assert_eq!(check(&source)[0].message, "Expected #Text for 'area' but found #Number.");
+
+    // Without `otherwise` a plain switch may match nothing, so it could be anything
+    assert!(check("size #Number is of 2 {\n  1 -> 10\n}").is_empty());
+}
+
+#[test]
+fn pattern_bindings_take_the_field_types() {
+    let source = format!("{SHAPE}
+shape #Shape is Circle(1)
+if shape = Circle(radius) {{
+  label #Text is radius
+}}");
+    assert_eq!(check(&source)[0].message, "Expected #Text for 'label' but found #Number.");
+}
+
+#[test]
+fn compound_annotations_are_checked() {
+    let diagnostics = check("scores #List(Number) is [\"one\", \"two\"]");
+    assert_eq!(diagnostics[0].message, "Expected #List(Number) for 'scores' but found #List(Text).");
+    assert!(check("scores #List(Number) is [1, 2]\nages #Map(Text, Integer) is {\"ann\": 3}").is_empty());
+
+    assert!(check("see #Age is #Integer\nage #Age is 3").is_empty());
+    assert_eq!(check("see #Age is #Integer\nage #Age is \"old\"").len(), 1);
+
+    assert!(check("id #Integer | #Text is \"a\"").is_empty());
+    assert_eq!(check("id #Integer | #Text is yes").len(), 1);
+
+    let diagnostics = check("double #(Integer) -> #Integer is (n #Text) -> n");
+    assert_eq!(diagnostics.len(), 1);
+    assert!(check("double #(Integer) -> #Number is (n #Integer) -> n * 2").is_empty());
+}
+
+const TREE: &str = "
+see #BinaryTree(T) is #Variant {
+  Leaf
+  Node(value #T, left #BinaryTree(T), right #BinaryTree(T))
+}
+see #Pair(A, B) is #Record {
+  first #A
+  second #B
+}
+";
+
+#[test]
+fn generic_types_take_their_arguments_from_use() {
+    assert!(check(&format!("{TREE}tree #BinaryTree(Integer) is Node(1, Node(2, Leaf, Leaf), Leaf)")).is_empty());
+    let diagnostics = check(&format!("{TREE}tree #BinaryTree(Text) is Node(1, Leaf, Leaf)"));
+    assert_eq!(diagnostics[0].message, "Expected #BinaryTree(Text) for 'tree' but found #BinaryTree(Integer).");
+
+    // Every subtree must hold the same element type as the node above it
+    let diagnostics = check(&format!("{TREE}Node(\"a\", Node(1, Leaf, Leaf), Leaf)"));
+    assert_eq!(diagnostics[0].message, "Expected #BinaryTree(Text) for 'left' of 'Node' but found #BinaryTree(Integer).");
+
+    let source = format!("{TREE}pair #Pair(Text, Number) is Pair {{ first: \"x\", second: 2 }}\nsize #Text is pair'second");
+    assert_eq!(check(&source)[0].message, "Expected #Text for 'size' but found #Number.");
+    let diagnostics = check(&format!("{TREE}pair #Pair(Text, Number) is Pair {{ first: 1, second: 2 }}"));
+    assert_eq!(diagnostics[0].message, "Expected #Pair(Text, Number) for 'pair' but found #Pair(Integer, Integer).");
+}
+
+#[test]
+fn patterns_on_generic_variants_bind_the_argument_types() {
+    let source = format!("{TREE}
+tree #BinaryTree(Text) is Leaf
+of tree {{
+  Node(value, left, right) -> {{
+    count #Integer is value
+  }}
+  Leaf -> 0
+}}");
+    assert_eq!(check(&source)[0].message, "Expected #Integer for 'count' but found #Text.");
+
+    let source = "found #Integer* is Some(3)\nif found = Some(n) {\n  label #Text is n\n}";
+    assert_eq!(check(source)[0].message, "Expected #Text for 'label' but found #Integer.");
+}
+
+#[test]
+fn tuples_are_checked_element_by_element() {
+    assert!(check("point #Tuple(Number, Number) is (1, 2.5)").is_empty());
+    let diagnostics = check("point #Tuple(Number, Number) is (1, \"two\")");
+    assert_eq!(diagnostics[0].message, "Expected #Tuple(Number, Number) for 'point' but found #Tuple(Integer, Text).");
+
+    let diagnostics = check("(a, b) is (1, 2, 3)");
+    assert_eq!(diagnostics[0].message, "Expected #Tuple(Any, Any) for '(a, b)' but found #Tuple(Integer, Integer, Integer).");
+
+    let diagnostics = check("(name, age) is (\"Ann\", 30)\nlabel #Text is age\nsecond #Text is (1, \"x\")[1]");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "Expected #Text for 'label' but found #Integer.");
+
+    let diagnostics = check("for entry in { \"a\": 1 } {\n  (key, value) is entry\n  label #Text is value\n}");
+    assert_eq!(diagnostics[0].message, "Expected #Text for 'label' but found #Integer.");
+}
+
+#[test]
+fn results_from_the_overview_type_check() {
+    let source = "
+see #Age is #Number
+divide #Result(Number) by {
+  @numerator #Number
+  @denominator #Number
+  if denominator = 0 {
+    produce Error(\"Division by zero\")
+  }
+  Success(numerator / denominator)
+}
+calculate-ratio #Result(Number) by {
+  @a #Number
+  @b #Number
+  @c #Number
+  first-result #Number is try divide(a, b)
+  second-result #Number is try divide(first-result, c)
+  Success(second-result)
+}
+calculate-complex #Result(Number) by {
+  @x #Number
+  @y #Number
+  quotient #Number is try divide(x, y) | (error) -> Error(\"Failed division: {error}\")
+  Success(quotient)
+}
+validate-age #Result(Age) by {
+  @age #Number
+  if age < 0 {
+    produce Error(\"Age cannot be negative\")
+  }
+  Success(age)
+}
+typed #Result(Number, Text) is divide(1, 2)";
+    assert!(check(source).is_empty(), "{:?}", check(source));
+
+    let diagnostics = check("wrong #Result(Number, Text) is Error(3)");
+    assert_eq!(diagnostics[0].message, "Expected #Result(Number, Text) for 'wrong' but found #Result(Any, Integer).");
+}
+
+#[test]
+fn type_arguments_must_match_the_definition() {
+    let diagnostics = check("pair #Result(Number, Text, Text) is Success(1)");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::InvalidTypeArguments);
+    assert_eq!(diagnostics[0].message, "#Result takes 1 to 2 type argument(s) but was given 3.");
+
+    let diagnostics = check(&format!("{TREE}tree #BinaryTree(Integer, Text) is Leaf"));
+    assert_eq!(diagnostics[0].message, "#BinaryTree takes 1 type argument(s) but was given 2.");
+    assert!(check(&format!("{TREE}tree #BinaryTree is Leaf")).is_empty());
+}
+
+#[test]
+fn annotations_must_name_declared_types() {
+    let diagnostics = check("age #Nubmer is 3");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::UndefinedType);
+    assert_eq!(diagnostics[0].message, "Undefined type #Nubmer.");
+
+    assert_eq!(check("names #List(Txet) is []")[0].message, "Undefined type #Txet.");
+    assert_eq!(check("see #Pair(A) is #Record { first #A\n second #B }")[0].message, "Undefined type #B.");
+    assert!(check(&format!("{TREE}{SHAPE}tree #BinaryTree(Shape) is Leaf\nshape #Shape* is None")).is_empty());
+}
+
+#[test]
+fn control_flow_must_stay_inside_its_construct() {
+    let diagnostics = check("if yes {\n  break\n}\ncontinue\nproduce 1");
+    let codes: Vec<_> = diagnostics.iter().map(|d| d.code).collect();
+    assert_eq!(codes, vec![ErrorCode::BreakOutsideLoop, ErrorCode::ContinueOutsideLoop, ErrorCode::ProduceOutsideFunction]);
+    assert_eq!((diagnostics[0].span().line, diagnostics[0].span().column), (2, 3));
+
+    // A loop around a function does not reach into its body
+    let diagnostics = check("while yes {\n  stop #Bliss by {\n    break\n  }\n  stop()\n}");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].code, ErrorCode::BreakOutsideLoop);
+
+    assert!(check("for n in 1..3 {\n  if n = 2 {\n    continue\n  }\n  break\n}").is_empty());
+    assert!(check("f #Bliss by {\n  produce\n}\ng #Any is () -> {\n  produce 1\n}").is_empty());
+}
+
+#[test]
+fn declarations_carry_over_to_later_inputs() {
+    let mut checker = TypeChecker::new();
+    let mut check_line = |source: &str| checker.check(&Parser::new(source).parse().0);
+
+    assert!(check_line("double #Integer by {\n  @n #Integer\n  n * 2\n}").is_empty());
+    assert_eq!(check_line("double(\"two\")")[0].message, "Expected #Integer for 'n' of 'double' but found #Text.");
+
+    // A rejected input never runs, so what it declared is forgotten
+    assert_eq!(check_line("name #Text is 1").len(), 1);
+    assert!(check_line("name #Integer is 1").is_empty());
+}